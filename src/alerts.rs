@@ -1,6 +1,10 @@
-use crate::cli::Matcher;
-use crate::plugin::Thresholds;
+use crate::cli::{Aggregate, Lines, Matcher, Resource};
+use crate::json_path::JsonPath;
+use crate::plugin::{Alert, Thresholds};
+use crate::process::{Signal, Usage};
 use humantime::format_duration;
+use regex::bytes::Regex;
+use serde_json::{json, Value};
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
 
@@ -23,6 +27,15 @@ impl Display for ExecTime {
     }
 }
 
+impl Alert for ExecTime {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "time",
+            "seconds": self.time.as_secs_f64(),
+        })
+    }
+}
+
 pub(crate) struct ExitCode {
     pub(crate) code: i32,
     pub(crate) thresholds: Thresholds,
@@ -41,6 +54,306 @@ impl Display for ExitCode {
     }
 }
 
+impl Alert for ExitCode {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "exit",
+            "code": self.code,
+        })
+    }
+}
+
+pub(crate) struct Killed {
+    pub(crate) signal: Option<Signal>,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for Killed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.signal {
+            None => write!(f, "Command wasn't killed by any signal.")?,
+            Some(signal) => {
+                write!(f, "Command was killed by signal {}", signal.number)?;
+
+                if !signal.description.is_empty() {
+                    write!(f, " ({})", signal.description)?;
+                }
+
+                if signal.core_dumped {
+                    write!(f, ", core dumped")?;
+                }
+
+                write!(f, ".")?;
+            }
+        }
+
+        AlertThresholds {
+            thresholds: self.thresholds.clone(),
+        }
+        .fmt(f)
+    }
+}
+
+impl Alert for Killed {
+    fn details(&self) -> Value {
+        match &self.signal {
+            None => json!({
+                "kind": "signal",
+                "signal": 0,
+            }),
+            Some(signal) => json!({
+                "kind": "signal",
+                "signal": signal.number,
+                "description": signal.description,
+                "core_dumped": signal.core_dumped,
+            }),
+        }
+    }
+}
+
+pub(crate) struct Leftovers {
+    pub(crate) processes: usize,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for Leftovers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command left {} processes behind which had to be terminated.{}",
+            self.processes,
+            AlertThresholds {
+                thresholds: self.thresholds.clone()
+            }
+        )
+    }
+}
+
+impl Alert for Leftovers {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "leftovers",
+            "processes": self.processes,
+        })
+    }
+}
+
+pub(crate) struct ResourceUsage {
+    pub(crate) resource: Resource,
+    pub(crate) usage: Usage,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for ResourceUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.resource {
+            Resource::CpuTime => write!(
+                f,
+                "Command used {} seconds of CPU time ({} user, {} system).",
+                (self.usage.user + self.usage.system).as_secs_f64(),
+                format_duration(self.usage.user),
+                format_duration(self.usage.system)
+            )?,
+            Resource::MaxRss => write!(
+                f,
+                "Command used at most {} bytes of memory.",
+                self.usage.max_rss
+            )?,
+            Resource::MajorFaults => write!(
+                f,
+                "Command caused {} major page faults.",
+                self.usage.major_faults
+            )?,
+            Resource::ContextSwitches => write!(
+                f,
+                "Command was context-switched {} times.",
+                self.usage.context_switches
+            )?,
+        }
+
+        AlertThresholds {
+            thresholds: self.thresholds.clone(),
+        }
+        .fmt(f)
+    }
+}
+
+impl Alert for ResourceUsage {
+    fn details(&self) -> Value {
+        json!({
+            "kind": self.resource.name(),
+            "user_seconds": self.usage.user.as_secs_f64(),
+            "system_seconds": self.usage.system.as_secs_f64(),
+            "max_rss": self.usage.max_rss,
+            "major_faults": self.usage.major_faults,
+            "context_switches": self.usage.context_switches,
+        })
+    }
+}
+
+pub(crate) struct NoUsage;
+
+impl Display for NoUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Resource usage isn't available on this platform.")
+    }
+}
+
+impl Alert for NoUsage {}
+
+pub(crate) struct Bytes {
+    pub(crate) source: &'static str,
+    pub(crate) total: u64,
+    pub(crate) kept: usize,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command's {} was {} bytes long.{}",
+            self.source,
+            self.total,
+            AlertThresholds {
+                thresholds: self.thresholds.clone()
+            }
+        )?;
+
+        if self.total > self.kept as u64 {
+            write!(f, " Only the first {} bytes were captured.", self.kept)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Alert for Bytes {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "bytes",
+            "source": self.source,
+            "total": self.total,
+            "kept": self.kept,
+        })
+    }
+}
+
+pub(crate) struct MatchingLines {
+    pub(crate) source: &'static str,
+    pub(crate) lines: Lines,
+    pub(crate) matcher: Matcher,
+    pub(crate) times: usize,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for MatchingLines {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command's {} had {} lines {} the following pattern.{}",
+            self.source,
+            self.times,
+            match self.lines {
+                Lines::Matching => "matching",
+                Lines::NotMatching => "not matching",
+            },
+            AlertThresholds {
+                thresholds: self.thresholds.clone()
+            }
+        )?;
+
+        MatcherDisplay {
+            matcher: &self.matcher,
+        }
+        .fmt(f)
+    }
+}
+
+impl Alert for MatchingLines {
+    fn details(&self) -> Value {
+        matcher_json(
+            json!({
+                "kind": self.lines.name(),
+                "source": self.source,
+                "count": self.times,
+            }),
+            &self.matcher,
+        )
+    }
+}
+
+pub(crate) struct Stopped {
+    pub(crate) source: &'static str,
+    pub(crate) matcher: Matcher,
+    pub(crate) after: Duration,
+}
+
+impl Display for Stopped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command's {} matched the following stop pattern after {} seconds ({}), so it was terminated.",
+            self.source,
+            self.after.as_secs_f64(),
+            format_duration(self.after)
+        )?;
+
+        MatcherDisplay {
+            matcher: &self.matcher,
+        }
+        .fmt(f)
+    }
+}
+
+impl Alert for Stopped {
+    fn details(&self) -> Value {
+        matcher_json(
+            json!({
+                "kind": "stopon",
+                "source": self.source,
+                "seconds": self.after.as_secs_f64(),
+            }),
+            &self.matcher,
+        )
+    }
+}
+
+pub(crate) struct TimedOut {
+    pub(crate) after: Duration,
+    /// Not the command itself, but processes it left behind, kept its output open.
+    pub(crate) held_open: bool,
+}
+
+impl Display for TimedOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.held_open {
+            true => write!(
+                f,
+                "Command exited, but processes it left behind kept its output open until the timeout after {} seconds ({}) and were terminated.",
+                self.after.as_secs_f64(),
+                format_duration(self.after)
+            ),
+            false => write!(
+                f,
+                "Command timed out after {} seconds ({}) and was terminated.",
+                self.after.as_secs_f64(),
+                format_duration(self.after)
+            ),
+        }
+    }
+}
+
+impl Alert for TimedOut {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "timeout",
+            "seconds": self.after.as_secs_f64(),
+            "held_open": self.held_open,
+        })
+    }
+}
+
 pub(crate) struct Matches {
     pub(crate) source: &'static str,
     pub(crate) matcher: Matcher,
@@ -60,7 +373,252 @@ impl Display for Matches {
             }
         )?;
 
-        match &self.matcher {
+        MatcherDisplay {
+            matcher: &self.matcher,
+        }
+        .fmt(f)
+    }
+}
+
+impl Alert for Matches {
+    fn details(&self) -> Value {
+        matcher_json(
+            json!({
+                "kind": "matches",
+                "source": self.source,
+                "count": self.times,
+            }),
+            &self.matcher,
+        )
+    }
+}
+
+pub(crate) struct Extracted {
+    pub(crate) source: &'static str,
+    pub(crate) regex: Regex,
+    pub(crate) aggregate: Aggregate,
+    pub(crate) count: usize,
+    pub(crate) key: String,
+    pub(crate) value: f64,
+    pub(crate) uom: String,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for Extracted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Command's {} reported ", self.source)?;
+
+        match self.aggregate {
+            Aggregate::Last => write!(f, "{}{}", self.value, self.uom)?,
+            Aggregate::Each => write!(f, "{}{} for {}", self.value, self.uom, self.key)?,
+            aggregate => write!(
+                f,
+                "{} numbers, {} {}{}",
+                self.count,
+                match aggregate {
+                    Aggregate::Sum => "summing up to",
+                    Aggregate::Min => "the least being",
+                    Aggregate::Max => "the greatest being",
+                    _ => "averaging",
+                },
+                self.value,
+                self.uom
+            )?,
+        }
+
+        write!(
+            f,
+            ".{} Regular expression: {}",
+            AlertThresholds {
+                thresholds: self.thresholds.clone()
+            },
+            self.regex
+        )
+    }
+}
+
+impl Alert for Extracted {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "extract",
+            "source": self.source,
+            "regex": self.regex.as_str(),
+            "aggregate": self.aggregate.name(),
+            "count": self.count,
+            "key": self.key,
+            "value": self.value,
+            "uom": self.uom,
+        })
+    }
+}
+
+pub(crate) struct NothingExtracted {
+    pub(crate) source: &'static str,
+    pub(crate) regex: Regex,
+}
+
+impl Display for NothingExtracted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command's {} contained no number matching the following pattern. Regular expression: {}",
+            self.source, self.regex
+        )
+    }
+}
+
+impl Alert for NothingExtracted {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "extract",
+            "source": self.source,
+            "regex": self.regex.as_str(),
+        })
+    }
+}
+
+pub(crate) struct JsonValue {
+    pub(crate) source: &'static str,
+    pub(crate) path: JsonPath,
+    pub(crate) value: f64,
+    pub(crate) counted: bool,
+    pub(crate) thresholds: Thresholds,
+}
+
+impl Display for JsonValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command's {} had {}{} at JSON path {}.{}",
+            self.source,
+            self.value,
+            if self.counted { " elements" } else { "" },
+            self.path,
+            AlertThresholds {
+                thresholds: self.thresholds.clone()
+            }
+        )
+    }
+}
+
+impl Alert for JsonValue {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "json",
+            "source": self.source,
+            "path": self.path.to_string(),
+            "value": self.value,
+            "counted": self.counted,
+        })
+    }
+}
+
+pub(crate) enum JsonProblem {
+    Truncated,
+    Malformed(serde_json::Error),
+    Missing,
+    NotNumeric,
+}
+
+pub(crate) struct BadJson {
+    pub(crate) source: &'static str,
+    pub(crate) path: JsonPath,
+    pub(crate) problem: JsonProblem,
+}
+
+impl Display for BadJson {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.problem {
+            JsonProblem::Truncated => write!(
+                f,
+                "Command's {} wasn't captured completely, so it can't be parsed as JSON.",
+                self.source
+            ),
+            JsonProblem::Malformed(err) => {
+                write!(f, "Command's {} isn't valid JSON: {}", self.source, err)
+            }
+            JsonProblem::Missing => write!(
+                f,
+                "Command's {} had nothing at JSON path {}.",
+                self.source, self.path
+            ),
+            JsonProblem::NotNumeric => write!(
+                f,
+                "Command's {} had no number at JSON path {}.",
+                self.source, self.path
+            ),
+        }
+    }
+}
+
+impl Alert for BadJson {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "json",
+            "source": self.source,
+            "path": self.path.to_string(),
+            "problem": match self.problem {
+                JsonProblem::Truncated => "truncated",
+                JsonProblem::Malformed(_) => "malformed",
+                JsonProblem::Missing => "missing",
+                JsonProblem::NotNumeric => "not_numeric",
+            },
+        })
+    }
+}
+
+pub(crate) struct Passthrough {
+    pub(crate) code: Option<i32>,
+    pub(crate) text: String,
+}
+
+impl Display for Passthrough {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            None => write!(f, "Command didn't return any state.")?,
+            Some(code) => write!(f, "Command returned state {}.", code)?,
+        }
+
+        if !self.text.is_empty() {
+            write!(f, " {}", self.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Alert for Passthrough {
+    fn details(&self) -> Value {
+        json!({
+            "kind": "passthrough",
+            "code": self.code,
+            "text": self.text,
+        })
+    }
+}
+
+/// Adds the pattern to an alert's details.
+fn matcher_json(mut details: Value, matcher: &Matcher) -> Value {
+    if let Value::Object(details) = &mut details {
+        let (kind, pattern) = match matcher {
+            Matcher::Literal(literal) => ("literal", String::from_utf8_lossy(literal)),
+            Matcher::RegExp(regexp) => ("regex", regexp.as_str().into()),
+        };
+
+        details.insert("matcher".to_owned(), kind.into());
+        details.insert("pattern".to_owned(), pattern.into());
+    }
+
+    details
+}
+
+struct MatcherDisplay<'a> {
+    matcher: &'a Matcher,
+}
+
+impl Display for MatcherDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.matcher {
             Matcher::Literal(literal) => {
                 write!(f, " Literal string: {}", String::from_utf8_lossy(literal))
             }
@@ -69,11 +627,35 @@ impl Display for Matches {
     }
 }
 
+struct AlertThresholds {
+    thresholds: Thresholds,
+}
+
+impl Display for AlertThresholds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.thresholds.warn {
+            None => {}
+            Some(warn) => {
+                write!(f, " Warning: {}.", warn)?;
+            }
+        }
+
+        match self.thresholds.crit {
+            None => {}
+            Some(crit) => {
+                write!(f, " Critical: {}.", crit)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::plugin::Thresholds;
-    use regex::bytes::Regex;
+    use nagios_range::NagiosRange;
     use std::time::Duration;
 
     fn no_thresholds() -> Thresholds {
@@ -116,6 +698,144 @@ mod tests {
         assert!(s.contains("0"));
     }
 
+    #[test]
+    fn test_killed_display() {
+        let k = Killed {
+            signal: Some(Signal {
+                number: 11,
+                description: String::from("Segmentation fault"),
+                core_dumped: true,
+            }),
+            thresholds: no_thresholds(),
+        };
+        let s = k.to_string();
+        assert!(s.contains("killed by signal 11 (Segmentation fault), core dumped."));
+    }
+
+    #[test]
+    fn test_not_killed_display() {
+        let k = Killed {
+            signal: None,
+            thresholds: no_thresholds(),
+        };
+        assert!(k.to_string().contains("wasn't killed"));
+    }
+
+    #[test]
+    fn test_leftovers_display() {
+        let lo = Leftovers {
+            processes: 2,
+            thresholds: no_thresholds(),
+        };
+        let s = lo.to_string();
+        assert!(s.contains("left 2 processes behind"));
+    }
+
+    fn usage() -> Usage {
+        Usage {
+            user: Duration::from_millis(1500),
+            system: Duration::from_millis(500),
+            max_rss: 4096,
+            major_faults: 7,
+            context_switches: 11,
+        }
+    }
+
+    #[test]
+    fn test_resource_usage_cpu_time_display() {
+        let ru = ResourceUsage {
+            resource: Resource::CpuTime,
+            usage: usage(),
+            thresholds: no_thresholds(),
+        };
+        let s = ru.to_string();
+        assert!(s.contains("2 seconds of CPU time"));
+        assert!(s.contains("1s 500ms user"));
+    }
+
+    #[test]
+    fn test_resource_usage_counters_display() {
+        let rss = ResourceUsage {
+            resource: Resource::MaxRss,
+            usage: usage(),
+            thresholds: no_thresholds(),
+        };
+        assert!(rss.to_string().contains("4096 bytes"));
+
+        let ctxsw = ResourceUsage {
+            resource: Resource::ContextSwitches,
+            usage: usage(),
+            thresholds: no_thresholds(),
+        };
+        assert!(ctxsw.to_string().contains("11 times"));
+    }
+
+    #[test]
+    fn test_bytes_display() {
+        let b = Bytes {
+            source: "stdout",
+            total: 100,
+            kept: 100,
+            thresholds: no_thresholds(),
+        };
+        let s = b.to_string();
+        assert!(s.contains("stdout was 100 bytes long"));
+        assert!(!s.contains("captured"));
+
+        let b = Bytes {
+            source: "stdout",
+            total: 100,
+            kept: 10,
+            thresholds: no_thresholds(),
+        };
+        assert!(b.to_string().contains("first 10 bytes were captured"));
+    }
+
+    #[test]
+    fn test_matching_lines_display() {
+        let ml = MatchingLines {
+            source: "stdout",
+            lines: Lines::NotMatching,
+            matcher: Matcher::Literal(b"#".to_vec()),
+            times: 4,
+            thresholds: no_thresholds(),
+        };
+        let s = ml.to_string();
+        assert!(s.contains("stdout had 4 lines not matching"));
+        assert!(s.contains("Literal string: #"));
+    }
+
+    #[test]
+    fn test_stopped_display() {
+        let st = Stopped {
+            source: "stderr",
+            matcher: Matcher::Literal(b"ready".to_vec()),
+            after: Duration::from_millis(1500),
+        };
+        let s = st.to_string();
+        assert!(s.contains("stderr matched the following stop pattern after 1.5 seconds"));
+        assert!(s.contains("Literal string: ready"));
+    }
+
+    #[test]
+    fn test_timed_out_display() {
+        let to = TimedOut {
+            after: Duration::from_secs(10),
+            held_open: false,
+        };
+        let s = to.to_string();
+        assert!(s.contains("timed out after 10 seconds"));
+        assert!(s.contains("terminated"));
+
+        let to = TimedOut {
+            after: Duration::from_secs(10),
+            held_open: true,
+        };
+        let s = to.to_string();
+        assert!(s.contains("left behind kept its output open"));
+        assert!(s.contains("after 10 seconds"));
+    }
+
     #[test]
     fn test_matches_literal_display() {
         let m = Matches {
@@ -158,28 +878,123 @@ mod tests {
         assert!(s.contains("0"));
         assert!(s.contains("needle"));
     }
-}
 
-struct AlertThresholds {
-    thresholds: Thresholds,
-}
+    #[test]
+    fn test_extracted_display() {
+        let ex = Extracted {
+            source: "stdout",
+            regex: Regex::new(r"(\d+)%").unwrap(),
+            aggregate: Aggregate::Last,
+            count: 2,
+            key: String::new(),
+            value: 93.0,
+            uom: "%".into(),
+            thresholds: Thresholds {
+                warn: Some(NagiosRange::from("80").unwrap()),
+                crit: None,
+            },
+        };
+        let s = ex.to_string();
+        assert!(s.contains("stdout reported 93%."));
+        assert!(s.contains(" Warning: "));
+        assert!(s.contains(r"Regular expression: (\d+)%"));
+    }
 
-impl Display for AlertThresholds {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self.thresholds.warn {
-            None => {}
-            Some(warn) => {
-                write!(f, " Warning: {}.", warn)?;
-            }
-        }
+    #[test]
+    fn test_extracted_aggregate_display() {
+        let mut ex = Extracted {
+            source: "stdout",
+            regex: Regex::new(r"(\d+)").unwrap(),
+            aggregate: Aggregate::Sum,
+            count: 3,
+            key: String::new(),
+            value: 12.0,
+            uom: "B".into(),
+            thresholds: no_thresholds(),
+        };
+        assert!(ex
+            .to_string()
+            .contains("stdout reported 3 numbers, summing up to 12B."));
 
-        match self.thresholds.crit {
-            None => {}
-            Some(crit) => {
-                write!(f, " Critical: {}.", crit)?;
-            }
-        }
+        ex.aggregate = Aggregate::Each;
+        ex.key = "sda".to_owned();
+        assert!(ex.to_string().contains("stdout reported 12B for sda."));
+    }
 
-        Ok(())
+    #[test]
+    fn test_nothing_extracted_display() {
+        let ne = NothingExtracted {
+            source: "stderr",
+            regex: Regex::new("used: (\\S+)").unwrap(),
+        };
+        assert!(ne.to_string().contains("stderr contained no number"));
+    }
+
+    #[test]
+    fn test_json_value_display() {
+        let jv = JsonValue {
+            source: "stdout",
+            path: JsonPath::parse("$.items[*]").unwrap(),
+            value: 3.0,
+            counted: true,
+            thresholds: no_thresholds(),
+        };
+        assert!(jv
+            .to_string()
+            .contains("stdout had 3 elements at JSON path $.items[*]."));
+    }
+
+    #[test]
+    fn test_bad_json_display() {
+        let bj = BadJson {
+            source: "stdout",
+            path: JsonPath::parse("$.size").unwrap(),
+            problem: JsonProblem::Malformed(serde_json::from_str::<u8>("{").unwrap_err()),
+        };
+        assert!(bj.to_string().contains("stdout isn't valid JSON: "));
+
+        let bj = BadJson {
+            problem: JsonProblem::Missing,
+            ..bj
+        };
+        assert!(bj.to_string().contains("had nothing at JSON path $.size."));
+    }
+
+    #[test]
+    fn test_passthrough_display() {
+        let pt = Passthrough {
+            code: Some(2),
+            text: "DISK CRITICAL - free space: / 12 MB".to_owned(),
+        };
+        assert_eq!(
+            pt.to_string(),
+            "Command returned state 2. DISK CRITICAL - free space: / 12 MB"
+        );
+
+        let pt = Passthrough {
+            code: None,
+            text: String::new(),
+        };
+        assert_eq!(pt.to_string(), "Command didn't return any state.");
+    }
+
+    #[test]
+    fn test_matches_details() {
+        let m = Matches {
+            source: "stderr",
+            matcher: Matcher::RegExp(Regex::new("err(or)?").unwrap()),
+            times: 2,
+            thresholds: no_thresholds(),
+        };
+        assert_eq!(
+            m.details(),
+            json!({
+                "kind": "matches",
+                "source": "stderr",
+                "count": 2,
+                "matcher": "regex",
+                "pattern": "err(or)?",
+            })
+        );
     }
 }
@@ -1,83 +1,564 @@
-use crate::cli::{Args, Condition, ExpectedArg, Matcher, ParseArgsError};
+use crate::cli::{
+    Aggregate, Args, Condition, Dump, EnvOp, ExpectedArg, Extraction, Format, Input, Limit, Lines,
+    Matcher, ParseArgsError, ParseErrors, Position, Request, Resource, Show, StopOn, Stream,
+    Timeout,
+};
 use crate::cnt_iter::CounterIterator;
-use crate::plugin::{Perfdat, Thresholds};
+use crate::config;
+use crate::grammar::{choices, PARAMS};
+use crate::json_path::JsonPath;
+use crate::plugin::{Perfdat, State, Thresholds};
+use crate::template::Template;
 use nagios_range::NagiosRange;
 use regex::bytes::Regex;
 use std::ffi::OsString;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+use std::vec::IntoIter;
 
-pub(crate) fn parse_cmdline<A>(
-    mut args: A,
-) -> (Option<OsString>, Result<Args, ParseArgsError>, usize)
+pub(crate) fn parse_cmdline<A>(mut args: A) -> (Option<OsString>, Result<Request, ParseErrors>)
 where
     A: Iterator<Item = OsString>,
 {
     let program = args.next();
-    let mut ci = CounterIterator::new(args);
+    let mut tokens = Tokens {
+        cli: CounterIterator::new(&mut args),
+        config: Vec::new().into_iter(),
+        file: Rc::from(""),
+        peeked: None,
+        position: Position::Argument(0),
+    };
 
-    (program, parse_args(&mut ci), ci.taken())
+    (program, parse_args(&mut tokens))
 }
 
-fn parse_args(args: &mut dyn Iterator<Item = OsString>) -> Result<Args, ParseArgsError> {
-    let mut cd = Vec::new();
-    let mut conditions = Vec::new();
+/// The CLI arguments with configuration files spliced in.
+/// Remembers where the last one came from.
+struct Tokens<'a> {
+    cli: CounterIterator<OsString, &'a mut dyn Iterator<Item = OsString>>,
+    config: IntoIter<config::Argument>,
+    file: Rc<str>,
+    peeked: Option<(Option<OsString>, Position)>,
+    position: Position,
+}
 
-    loop {
-        match require_noempty_utf8(args.next(), ExpectedArg::Command)?.as_ref() {
-            "time" => {
-                conditions.push(Condition::ExecSeconds(parse_perfdata(args)?));
-            }
-            "exit" => {
-                conditions.push(Condition::ExitCode(parse_perfdata(args)?));
+impl Tokens<'_> {
+    /// Continues with the arguments a configuration file stands for.
+    fn include(&mut self, path: OsString) -> Result<(), ParseArgsError> {
+        let source = std::fs::read_to_string(&path).map_err(ParseArgsError::UnreadableConfig)?;
+
+        self.file = Rc::from(Path::new(&path).to_string_lossy());
+
+        match config::translate(&source) {
+            Err((err, line)) => {
+                self.position = Position::Line(self.file.clone(), line);
+                Err(ParseArgsError::BadConfig(err))
             }
-            "stdout" => {
-                conditions.push(Condition::Stdout(
-                    parse_matcher(args)?,
-                    parse_perfdata(args)?,
-                ));
+            Ok(arguments) => {
+                self.config = arguments.into_iter();
+                Ok(())
             }
-            "stderr" => {
-                conditions.push(Condition::Stderr(
-                    parse_matcher(args)?,
-                    parse_perfdata(args)?,
-                ));
+        }
+    }
+
+    /// Takes what's left of the configuration file being read.
+    fn rest_of_config(&mut self) -> Vec<OsString> {
+        let mut rest = Vec::new();
+
+        while self.peek().is_some() && matches!(self.peeked, Some((_, Position::Line(..)))) {
+            rest.extend(self.next());
+        }
+
+        rest
+    }
+
+    fn peek(&mut self) -> Option<&OsString> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.fetch());
+        }
+
+        self.peeked.as_ref().and_then(|(arg, _)| arg.as_ref())
+    }
+
+    fn fetch(&mut self) -> (Option<OsString>, Position) {
+        match self.config.next() {
+            Some((arg, line)) => (Some(arg), Position::Line(self.file.clone(), line)),
+            None => {
+                let arg = self.cli.next();
+                (arg, Position::Argument(self.cli.taken()))
             }
-            "cd" => {
-                cd.push(require_noempty(args.next(), ExpectedArg::WorkDir)?);
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = OsString;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (arg, position) = match self.peeked.take() {
+            None => self.fetch(),
+            Some(peeked) => peeked,
+        };
+
+        self.position = position;
+        arg
+    }
+}
+
+fn parse_args(args: &mut Tokens) -> Result<Request, ParseErrors> {
+    let mut todo = Args {
+        cd: Vec::new(),
+        env: Vec::new(),
+        stdin: None,
+        exe: OsString::new(),
+        args: Vec::new(),
+        conditions: Vec::new(),
+        timeout: None,
+        max_output: None,
+        stop_on: Vec::new(),
+        passthrough: None,
+        format: Format::Text,
+        ascii: false,
+        summary: None,
+        verbose: false,
+        dump: Dump {
+            stdout: Show::Problem,
+            stderr: Show::Problem,
+            limit: None,
+            context: None,
+        },
+        redact: Vec::new(),
+    };
+    let mut validate = false;
+    let mut errors = Vec::new();
+
+    // What's requested instead of running the command, if anything
+    let other = loop {
+        let result = match require_noempty_utf8(args.next(), ExpectedArg::Command) {
+            Err(err) => Err(err),
+            Ok(keyword) => match keyword.as_ref() {
+                "validate" => {
+                    validate = true;
+                    Ok(())
+                }
+                "help" => {
+                    break match args.next() {
+                        None => Ok(Some(Request::Help(None))),
+                        Some(param) => match param.to_str() {
+                            Some(name) if PARAMS.iter().any(|param| param.name == name) => {
+                                Ok(Some(Request::Help(Some(name.to_owned()))))
+                            }
+                            _ => Err(ParseArgsError::UnknownParam),
+                        },
+                    };
+                }
+                "version" => break Ok(Some(Request::Version)),
+                "command" => {
+                    let from_config = matches!(args.position, Position::Line(..));
+
+                    break require_noempty(args.next(), ExpectedArg::Exe).and_then(|exe| {
+                        todo.exe = exe;
+
+                        if !from_config {
+                            todo.args = args.collect();
+                            return Ok(None);
+                        }
+
+                        todo.args = args.rest_of_config();
+
+                        // The command of a configuration file comes last
+                        match args.next() {
+                            None => Ok(None),
+                            Some(_) => Err(ParseArgsError::AfterConfigCommand),
+                        }
+                    });
+                }
+                // The grammar table decides what is a parameter, not parse_param()
+                keyword if PARAMS.iter().any(|param| param.name == keyword) => {
+                    parse_param(keyword, args, &mut todo)
+                }
+                _ => Err(ParseArgsError::UnknownParam),
+            },
+        };
+
+        match result {
+            Ok(()) => {}
+            // Nothing left to recover from
+            Err(err @ ParseArgsError::UnexpectedEnd(_)) => break Err(err),
+            Err(err) => {
+                errors.push((err, args.position.clone()));
+
+                // Skip the rest of the broken parameter
+                while args.peek().is_some_and(|arg| !is_param(arg)) {
+                    args.next();
+                }
             }
-            "command" => {
-                return Ok(Args {
-                    cd,
-                    exe: require_noempty(args.next(), ExpectedArg::Exe)?,
-                    args: args.collect(),
-                    conditions,
-                })
+        }
+    };
+
+    match other {
+        Err(err) => errors.push((err, args.position.clone())),
+        Ok(_) if !errors.is_empty() => {}
+        Ok(Some(request)) => return Ok(request),
+        Ok(None) => {
+            return Ok(match validate {
+                true => Request::Validate(Box::new(todo)),
+                false => Request::Run(Box::new(todo)),
+            })
+        }
+    }
+
+    Err(ParseErrors {
+        errors,
+        partial: Box::new(todo),
+    })
+}
+
+/// Whether an argument starts another parameter.
+fn is_param(arg: &OsString) -> bool {
+    arg.to_str()
+        .is_some_and(|arg| PARAMS.iter().any(|param| param.name == arg))
+}
+
+fn parse_param(keyword: &str, args: &mut Tokens, todo: &mut Args) -> Result<(), ParseArgsError> {
+    match keyword {
+        "time" => {
+            todo.conditions
+                .push(Condition::ExecSeconds(parse_perfdata(args)?));
+        }
+        "exit" => {
+            todo.conditions
+                .push(Condition::ExitCode(parse_perfdata(args)?));
+        }
+        "signal" => {
+            todo.conditions
+                .push(Condition::Signal(parse_perfdata(args)?));
+        }
+        "leftovers" => {
+            todo.conditions
+                .push(Condition::Leftovers(parse_perfdata(args)?));
+        }
+        "cputime" => {
+            todo.conditions
+                .push(Condition::Usage(Resource::CpuTime, parse_perfdata(args)?));
+        }
+        "maxrss" => {
+            todo.conditions
+                .push(Condition::Usage(Resource::MaxRss, parse_perfdata(args)?));
+        }
+        "majflt" => {
+            todo.conditions.push(Condition::Usage(
+                Resource::MajorFaults,
+                parse_perfdata(args)?,
+            ));
+        }
+        "ctxsw" => {
+            todo.conditions.push(Condition::Usage(
+                Resource::ContextSwitches,
+                parse_perfdata(args)?,
+            ));
+        }
+        "stdout" => {
+            todo.conditions.push(parse_stream(args, Stream::Stdout)?);
+        }
+        "stderr" => {
+            todo.conditions.push(parse_stream(args, Stream::Stderr)?);
+        }
+        "cd" => {
+            todo.cd
+                .push(require_noempty(args.next(), ExpectedArg::WorkDir)?);
+        }
+        "env" => {
+            todo.env.push(EnvOp::Set(
+                require_noempty(args.next(), ExpectedArg::EnvName)?,
+                require(args.next(), ExpectedArg::EnvValue)?,
+            ));
+        }
+        "unenv" => {
+            todo.env.push(EnvOp::Unset(require_noempty(
+                args.next(),
+                ExpectedArg::EnvName,
+            )?));
+        }
+        "cleanenv" => {
+            todo.env.push(EnvOp::Clear);
+        }
+        "stdin" => {
+            todo.stdin = Some(parse_input(args)?);
+        }
+        "timeout" => {
+            todo.timeout = Some(Timeout {
+                after: require_seconds(args.next())?,
+                state: require_state(args.next())?,
+            });
+        }
+        "maxoutput" => {
+            todo.max_output = Some(require_bytes(args.next())?);
+        }
+        "stopon" => {
+            todo.stop_on.push(StopOn {
+                stream: require_stream(args.next())?,
+                matcher: parse_matcher(args)?,
+            });
+        }
+        "passthrough" => {
+            todo.passthrough = Some(require_utf8(args.next(), ExpectedArg::Prefix)?);
+        }
+        "output" => {
+            todo.format = match require_choice(
+                args.next(),
+                ExpectedArg::Format,
+                ParseArgsError::UnknownFormat,
+            )? {
+                "text" => Format::Text,
+                "json" => Format::Json,
+                _ => return Err(ParseArgsError::UnknownFormat),
+            };
+        }
+        "ascii" => {
+            todo.ascii = true;
+        }
+        "summary" => {
+            todo.summary = Some(
+                Template::parse(require_utf8(args.next(), ExpectedArg::Template)?.as_ref())
+                    .ok_or(ParseArgsError::BadTemplate)?,
+            );
+        }
+        "verbose" => {
+            todo.verbose = true;
+        }
+        "show" => {
+            let stream = require_stream(args.next())?;
+            let show = match require_choice(
+                args.next(),
+                ExpectedArg::Show,
+                ParseArgsError::UnknownShow,
+            )? {
+                "never" => Show::Never,
+                "problem" => Show::Problem,
+                "always" => Show::Always,
+                _ => return Err(ParseArgsError::UnknownShow),
+            };
+
+            match stream {
+                Stream::Stdout => todo.dump.stdout = show,
+                Stream::Stderr => todo.dump.stderr = show,
             }
-            _ => return Err(ParseArgsError::UnknownParam),
+        }
+        "showlimit" => {
+            todo.dump.limit = Some(
+                match require_choice(
+                    args.next(),
+                    ExpectedArg::Limit,
+                    ParseArgsError::UnknownLimit,
+                )? {
+                    "lines" => {
+                        Limit::Lines(require_count(args.next())?, require_count(args.next())?)
+                    }
+                    "bytes" => {
+                        Limit::Bytes(require_count(args.next())?, require_count(args.next())?)
+                    }
+                    _ => return Err(ParseArgsError::UnknownLimit),
+                },
+            );
+        }
+        "showmatching" => {
+            todo.dump.context = Some(require_count(args.next())?);
+        }
+        "redact" => {
+            todo.redact.push(parse_matcher(args)?);
+        }
+        "config" => {
+            let path = require_noempty(args.next(), ExpectedArg::ConfigFile)?;
+            args.include(path)?;
+        }
+        _ => return Err(ParseArgsError::UnknownParam),
+    }
+
+    Ok(())
+}
+
+fn parse_stream(args: &mut Tokens, stream: Stream) -> Result<Condition, ParseArgsError> {
+    match require_choice(
+        args.next(),
+        ExpectedArg::Matcher,
+        ParseArgsError::UnknownMatcher,
+    )? {
+        "bytes" => Ok(Condition::Bytes(stream, parse_perfdata(args)?)),
+        "lines" => Ok(Condition::Lines(
+            stream,
+            Lines::Matching,
+            parse_matcher(args)?,
+            parse_perfdata(args)?,
+        )),
+        "inverted" => Ok(Condition::Lines(
+            stream,
+            Lines::NotMatching,
+            parse_matcher(args)?,
+            parse_perfdata(args)?,
+        )),
+        "extract" => parse_extraction(args, stream, Aggregate::Last),
+        "json" => Ok(Condition::Json(
+            stream,
+            JsonPath::parse(require_noempty_utf8(args.next(), ExpectedArg::JsonPath)?.as_ref())
+                .ok_or(ParseArgsError::BadJsonPath)?,
+            parse_perfdata(args)?,
+        )),
+        "aggregate" => {
+            let aggregate = match require_choice(
+                args.next(),
+                ExpectedArg::Aggregate,
+                ParseArgsError::UnknownAggregate,
+            )? {
+                "sum" => Aggregate::Sum,
+                "min" => Aggregate::Min,
+                "max" => Aggregate::Max,
+                "avg" => Aggregate::Avg,
+                "last" => Aggregate::Last,
+                "each" => Aggregate::Each,
+                _ => return Err(ParseArgsError::UnknownAggregate),
+            };
+
+            parse_extraction(args, stream, aggregate)
+        }
+        kind => {
+            let matcher = parse_pattern(kind, args)?;
+            let perfdata = parse_perfdata(args)?;
+
+            Ok(match stream {
+                Stream::Stdout => Condition::Stdout(matcher, perfdata),
+                Stream::Stderr => Condition::Stderr(matcher, perfdata),
+            })
         }
     }
 }
 
-fn parse_matcher(args: &mut dyn Iterator<Item = OsString>) -> Result<Matcher, ParseArgsError> {
-    match require_noempty_utf8(args.next(), ExpectedArg::Matcher)?.as_ref() {
+fn parse_extraction(
+    args: &mut Tokens,
+    stream: Stream,
+    aggregate: Aggregate,
+) -> Result<Condition, ParseArgsError> {
+    let regex = Regex::new(require_noempty_utf8(args.next(), ExpectedArg::Pattern)?.as_ref())
+        .map_err(ParseArgsError::BadRegex)?;
+    let (perfdata, uom) = parse_measurement(args, true)?;
+
+    Ok(Condition::Extract(
+        stream,
+        Extraction {
+            regex,
+            uom,
+            aggregate,
+        },
+        perfdata,
+    ))
+}
+
+fn parse_matcher(args: &mut Tokens) -> Result<Matcher, ParseArgsError> {
+    parse_pattern(
+        require_choice(
+            args.next(),
+            ExpectedArg::PatternKind,
+            ParseArgsError::UnknownPatternKind,
+        )?,
+        args,
+    )
+}
+
+fn parse_pattern(kind: &str, args: &mut Tokens) -> Result<Matcher, ParseArgsError> {
+    match kind {
         "literal" => Ok(Matcher::Literal(
             require_noempty(args.next(), ExpectedArg::Pattern)?.into_encoded_bytes(),
         )),
         "regex" => Ok(Matcher::RegExp(
             Regex::new(require_noempty_utf8(args.next(), ExpectedArg::Pattern)?.as_ref())
-                .map_err(|err| ParseArgsError::BadRegex(err))?,
+                .map_err(ParseArgsError::BadRegex)?,
         )),
         _ => Err(ParseArgsError::UnknownMatcher),
     }
 }
 
-fn parse_perfdata(args: &mut dyn Iterator<Item = OsString>) -> Result<Perfdat, ParseArgsError> {
-    Ok(Perfdat {
+fn parse_input(args: &mut Tokens) -> Result<Input, ParseArgsError> {
+    match require_choice(
+        args.next(),
+        ExpectedArg::Input,
+        ParseArgsError::UnknownInput,
+    )? {
+        "literal" => Ok(Input::Literal(require(
+            args.next(),
+            ExpectedArg::InputData,
+        )?)),
+        "file" => Ok(Input::File(require_noempty(
+            args.next(),
+            ExpectedArg::InputFile,
+        )?)),
+        "null" => Ok(Input::Null),
+        _ => Err(ParseArgsError::UnknownInput),
+    }
+}
+
+fn parse_perfdata(args: &mut Tokens) -> Result<Perfdat, ParseArgsError> {
+    Ok(parse_measurement(args, false)?.0)
+}
+
+/// Parses WARN CRIT LABEL (and UOM if wanted) or alternatively
+/// any of warn=WARN crit=CRIT label=LABEL (uom=UOM) in any order, each defaulting to empty.
+fn parse_measurement(args: &mut Tokens, uom: bool) -> Result<(Perfdat, String), ParseArgsError> {
+    let mut named = false;
+    let mut unit = String::new();
+    let mut perfdata = Perfdat {
         thresholds: Thresholds {
-            warn: require_threshold(args.next(), ExpectedArg::Warning)?,
-            crit: require_threshold(args.next(), ExpectedArg::Critical)?,
+            warn: None,
+            crit: None,
         },
-        label: require_utf8(args.next(), ExpectedArg::Label)?,
-    })
+        label: String::new(),
+    };
+
+    while let Some((name, value)) = next_named(args, uom) {
+        match name {
+            "warn" => {
+                perfdata.thresholds.warn = require_threshold(Some(value), ExpectedArg::Warning)?
+            }
+            "crit" => {
+                perfdata.thresholds.crit = require_threshold(Some(value), ExpectedArg::Critical)?
+            }
+            "label" => perfdata.label = require_utf8(Some(value), ExpectedArg::Label)?,
+            _ => unit = require_unit(Some(value))?,
+        }
+
+        named = true;
+    }
+
+    if !named {
+        perfdata = Perfdat {
+            thresholds: Thresholds {
+                warn: require_threshold(args.next(), ExpectedArg::Warning)?,
+                crit: require_threshold(args.next(), ExpectedArg::Critical)?,
+            },
+            label: require_utf8(args.next(), ExpectedArg::Label)?,
+        };
+
+        // Optional, so the next parameter may follow right away
+        if uom && args.peek().is_some_and(|arg| !is_param(arg)) {
+            unit = require_unit(args.next())?;
+        }
+    }
+
+    Ok((perfdata, unit))
+}
+
+/// Consumes the next argument if it's a named option, e.g. `crit=1:`.
+fn next_named(args: &mut Tokens, uom: bool) -> Option<(&'static str, OsString)> {
+    let name = match args.peek()?.to_str()?.split_once('=')?.0 {
+        "warn" => "warn",
+        "crit" => "crit",
+        "label" => "label",
+        "uom" if uom => "uom",
+        _ => return None,
+    };
+    let arg = args.next()?.into_string().ok()?;
+
+    Some((name, OsString::from(&arg[name.len() + 1..])))
 }
 
 fn require_threshold(
@@ -95,6 +576,76 @@ fn require_threshold(
     }
 }
 
+fn require_seconds(oarg: Option<OsString>) -> Result<Duration, ParseArgsError> {
+    match require_noempty_utf8(oarg, ExpectedArg::Seconds)?.parse::<f64>() {
+        Ok(secs) if secs > 0.0 => {
+            Duration::try_from_secs_f64(secs).map_err(|_| ParseArgsError::BadSeconds)
+        }
+        _ => Err(ParseArgsError::BadSeconds),
+    }
+}
+
+fn require_bytes(oarg: Option<OsString>) -> Result<usize, ParseArgsError> {
+    require_noempty_utf8(oarg, ExpectedArg::Bytes)?
+        .parse()
+        .map_err(|_| ParseArgsError::BadBytes)
+}
+
+fn require_count(oarg: Option<OsString>) -> Result<usize, ParseArgsError> {
+    require_noempty_utf8(oarg, ExpectedArg::Count)?
+        .parse()
+        .map_err(|_| ParseArgsError::BadCount)
+}
+
+fn require_stream(oarg: Option<OsString>) -> Result<Stream, ParseArgsError> {
+    match require_choice(oarg, ExpectedArg::Stream, ParseArgsError::UnknownStream)? {
+        "stdout" => Ok(Stream::Stdout),
+        "stderr" => Ok(Stream::Stderr),
+        _ => Err(ParseArgsError::UnknownStream),
+    }
+}
+
+/// Returns a unit of measurement which can't break the perfdata syntax.
+fn require_unit(oarg: Option<OsString>) -> Result<String, ParseArgsError> {
+    let uom = require_utf8(oarg, ExpectedArg::Unit)?;
+
+    if uom
+        .chars()
+        .any(|c| c.is_ascii_digit() || c.is_whitespace() || "'=;.-".contains(c))
+    {
+        Err(ParseArgsError::BadUnit)
+    } else {
+        Ok(uom)
+    }
+}
+
+fn require_state(oarg: Option<OsString>) -> Result<State, ParseArgsError> {
+    match require_choice(oarg, ExpectedArg::State, ParseArgsError::UnknownState)? {
+        "ok" => Ok(State::Ok),
+        "warning" => Ok(State::Warning),
+        "critical" => Ok(State::Critical),
+        "unknown" => Ok(State::Unknown),
+        _ => Err(ParseArgsError::UnknownState),
+    }
+}
+
+/// Returns the argument if it's one of the words the grammar allows for it,
+/// so that the parser and the help can't disagree.
+fn require_choice(
+    oarg: Option<OsString>,
+    want: ExpectedArg,
+    unknown: ParseArgsError,
+) -> Result<&'static str, ParseArgsError> {
+    let arg = require_noempty_utf8(oarg, want.clone())?;
+
+    choices(&want)
+        .unwrap_or_default()
+        .iter()
+        .find(|word| **word == arg)
+        .copied()
+        .ok_or(unknown)
+}
+
 fn require_noempty_utf8(
     oarg: Option<OsString>,
     want: ExpectedArg,
@@ -130,16 +681,32 @@ mod tests {
         v.into_iter().map(OsString::from)
     }
 
-    fn unwrap_args(result: Result<Args, ParseArgsError>) -> Args {
+    /// Parses and returns only the first error, if any, and where it happened.
+    fn parse(
+        args: impl Iterator<Item = OsString>,
+    ) -> (Option<OsString>, Result<Request, ParseArgsError>, Position) {
+        let (program, result) = parse_cmdline(args);
+
         match result {
-            Ok(v) => v,
+            Ok(request) => (program, Ok(request), Position::Argument(0)),
+            Err(mut rejected) => {
+                let (err, position) = rejected.errors.remove(0);
+                (program, Err(err), position)
+            }
+        }
+    }
+
+    fn unwrap_args(result: Result<Request, ParseArgsError>) -> Args {
+        match result {
+            Ok(Request::Run(v)) => *v,
+            Ok(_) => panic!("unexpected request"),
             Err(_) => panic!("unexpected parse error"),
         }
     }
 
     #[test]
     fn test_minimal_command() {
-        let (prog, result, _) = parse_cmdline(args(vec!["prog", "command", "exe"]));
+        let (prog, result, _) = parse(args(vec!["prog", "command", "exe"]));
         assert_eq!(prog, Some(OsString::from("prog")));
         let parsed = unwrap_args(result);
         assert_eq!(parsed.exe, OsString::from("exe"));
@@ -150,7 +717,7 @@ mod tests {
 
     #[test]
     fn test_command_with_args() {
-        let (_, result, _) = parse_cmdline(args(vec!["prog", "command", "exe", "arg1", "arg2"]));
+        let (_, result, _) = parse(args(vec!["prog", "command", "exe", "arg1", "arg2"]));
         let parsed = unwrap_args(result);
         assert_eq!(parsed.exe, OsString::from("exe"));
         assert_eq!(
@@ -161,14 +728,14 @@ mod tests {
 
     #[test]
     fn test_cd_option() {
-        let (_, result, _) = parse_cmdline(args(vec!["prog", "cd", "/tmp", "command", "exe"]));
+        let (_, result, _) = parse(args(vec!["prog", "cd", "/tmp", "command", "exe"]));
         let parsed = unwrap_args(result);
         assert_eq!(parsed.cd, vec![OsString::from("/tmp")]);
     }
 
     #[test]
     fn test_time_condition() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog", "time", "", "", "label", "command", "exe",
         ]));
         let parsed = unwrap_args(result);
@@ -185,7 +752,7 @@ mod tests {
 
     #[test]
     fn test_exit_condition() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog",
             "exit",
             "",
@@ -204,9 +771,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_signal_condition() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "signal", "", "1:", "sig", "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 1);
+        match &parsed.conditions[0] {
+            Condition::Signal(pd) => {
+                assert!(pd.thresholds.crit.is_some());
+                assert_eq!(pd.label, "sig");
+            }
+            _ => panic!("expected Signal"),
+        }
+    }
+
+    #[test]
+    fn test_leftovers_condition() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "leftovers",
+            "",
+            "0:0",
+            "stray",
+            "command",
+            "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 1);
+        match &parsed.conditions[0] {
+            Condition::Leftovers(pd) => {
+                assert!(pd.thresholds.crit.is_some());
+                assert_eq!(pd.label, "stray");
+            }
+            _ => panic!("expected Leftovers"),
+        }
+    }
+
+    #[test]
+    fn test_usage_conditions() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "cputime", "", "", "cpu", "maxrss", "", "", "rss", "majflt", "", "", "",
+            "ctxsw", "", "", "", "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 4);
+        assert!(matches!(
+            &parsed.conditions[0],
+            Condition::Usage(Resource::CpuTime, pd) if pd.label == "cpu"
+        ));
+        assert!(matches!(
+            &parsed.conditions[1],
+            Condition::Usage(Resource::MaxRss, pd) if pd.label == "rss"
+        ));
+        assert!(matches!(
+            &parsed.conditions[2],
+            Condition::Usage(Resource::MajorFaults, _)
+        ));
+        assert!(matches!(
+            &parsed.conditions[3],
+            Condition::Usage(Resource::ContextSwitches, _)
+        ));
+    }
+
     #[test]
     fn test_stdout_literal_condition() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog", "stdout", "literal", "pattern", "", "", "", "command", "exe",
         ]));
         let parsed = unwrap_args(result);
@@ -221,7 +852,7 @@ mod tests {
 
     #[test]
     fn test_stderr_regex_condition() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog", "stderr", "regex", "foo.*", "", "", "", "command", "exe",
         ]));
         let parsed = unwrap_args(result);
@@ -234,34 +865,549 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bytes_condition() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stderr", "bytes", "", "0:0", "errbytes", "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 1);
+        match &parsed.conditions[0] {
+            Condition::Bytes(Stream::Stderr, pd) => {
+                assert_eq!(pd.label, "errbytes");
+            }
+            _ => panic!("expected Bytes(Stderr)"),
+        }
+    }
+
+    #[test]
+    fn test_lines_conditions() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stdout", "lines", "regex", "err", "", "", "", "stderr", "inverted", "literal",
+            "#", "", "", "", "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 2);
+        assert!(matches!(
+            &parsed.conditions[0],
+            Condition::Lines(Stream::Stdout, Lines::Matching, Matcher::RegExp(_), _)
+        ));
+        assert!(matches!(
+            &parsed.conditions[1],
+            Condition::Lines(Stream::Stderr, Lines::NotMatching, Matcher::Literal(_), _)
+        ));
+    }
+
+    #[test]
+    fn test_extract_condition() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "stdout",
+            "extract",
+            r"(\d+)%",
+            "80",
+            "90",
+            "usage",
+            "%",
+            "stderr",
+            "extract",
+            "took (\\S+)",
+            "",
+            "",
+            "",
+            "",
+            "command",
+            "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 2);
+        assert!(matches!(
+            &parsed.conditions[0],
+            Condition::Extract(Stream::Stdout, Extraction { uom, .. }, _) if uom == "%"
+        ));
+        assert!(matches!(
+            &parsed.conditions[1],
+            Condition::Extract(Stream::Stderr, Extraction { uom, .. }, _) if uom.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_extract_condition_without_unit() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stdout", "extract", r"(\d+)", "", "", "usage", "command", "sh", "-c", "echo 5",
+        ]));
+        let parsed = unwrap_args(result);
+        assert!(matches!(
+            &parsed.conditions[0],
+            Condition::Extract(Stream::Stdout, Extraction { uom, .. }, pd)
+                if uom.is_empty() && pd.label == "usage"
+        ));
+        assert_eq!(parsed.exe, OsString::from("sh"));
+        assert_eq!(
+            parsed.args,
+            vec![OsString::from("-c"), OsString::from("echo 5")]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_condition() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "stdout",
+            "aggregate",
+            "each",
+            "(?P<label>\\w+) (\\d+)",
+            "",
+            "",
+            "disk",
+            "B",
+            "command",
+            "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert!(matches!(
+            &parsed.conditions[0],
+            Condition::Extract(
+                Stream::Stdout,
+                Extraction {
+                    aggregate: Aggregate::Each,
+                    ..
+                },
+                _
+            )
+        ));
+
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "stdout",
+            "aggregate",
+            "median",
+            "(\\d+)",
+            "",
+            "",
+            "",
+            "",
+            "command",
+            "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownAggregate)));
+    }
+
+    #[test]
+    fn test_json_condition() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "stdout",
+            "json",
+            "$.items[*]",
+            "",
+            "",
+            "items",
+            "command",
+            "exe",
+        ]));
+        assert!(matches!(
+            &unwrap_args(result).conditions[0],
+            Condition::Json(Stream::Stdout, _, _)
+        ));
+
+        let (_, result, _) = parse(args(vec![
+            "prog", "stdout", "json", "$.items[", "", "", "", "command", "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::BadJsonPath)));
+    }
+
+    #[test]
+    fn test_passthrough_option() {
+        let (_, result, _) = parse(args(vec!["prog", "command", "exe"]));
+        assert_eq!(unwrap_args(result).passthrough, None);
+
+        let (_, result, _) = parse(args(vec!["prog", "passthrough", "", "command", "exe"]));
+        assert_eq!(unwrap_args(result).passthrough, Some(String::new()));
+
+        let (_, result, _) = parse(args(vec!["prog", "passthrough"]));
+        assert!(matches!(
+            result,
+            Err(ParseArgsError::UnexpectedEnd(ExpectedArg::Prefix))
+        ));
+    }
+
+    #[test]
+    fn test_output_option() {
+        let (_, result, _) = parse(args(vec!["prog", "command", "exe"]));
+        assert_eq!(unwrap_args(result).format, Format::Text);
+
+        let (_, result, _) = parse(args(vec!["prog", "output", "json", "command", "exe"]));
+        assert_eq!(unwrap_args(result).format, Format::Json);
+
+        let (_, result, _) = parse(args(vec!["prog", "output", "xml", "command", "exe"]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownFormat)));
+    }
+
+    #[test]
+    fn test_ascii_option() {
+        let (_, result, _) = parse(args(vec!["prog", "command", "exe"]));
+        assert!(!unwrap_args(result).ascii);
+
+        let (_, result, _) = parse(args(vec!["prog", "ascii", "command", "exe"]));
+        assert!(unwrap_args(result).ascii);
+    }
+
+    #[test]
+    fn test_summary_option() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "summary",
+            "{state}: {errors} errors",
+            "command",
+            "exe",
+        ]));
+        assert!(unwrap_args(result).summary.is_some());
+
+        let (_, result, _) = parse(args(vec!["prog", "summary", "{state", "command", "exe"]));
+        assert!(matches!(result, Err(ParseArgsError::BadTemplate)));
+    }
+
+    #[test]
+    fn test_verbose_option() {
+        let (_, result, _) = parse(args(vec!["prog", "command", "exe"]));
+        assert!(!unwrap_args(result).verbose);
+
+        let (_, result, _) = parse(args(vec!["prog", "verbose", "command", "exe"]));
+        assert!(unwrap_args(result).verbose);
+    }
+
+    #[test]
+    fn test_dump_options() {
+        let (_, result, _) = parse(args(vec!["prog", "command", "exe"]));
+        let dump = unwrap_args(result).dump;
+        assert_eq!(dump.stdout, Show::Problem);
+        assert_eq!(dump.stderr, Show::Problem);
+        assert_eq!(dump.limit, None);
+        assert_eq!(dump.context, None);
+
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "show",
+            "stderr",
+            "never",
+            "show",
+            "stdout",
+            "always",
+            "showlimit",
+            "lines",
+            "10",
+            "20",
+            "showmatching",
+            "2",
+            "command",
+            "exe",
+        ]));
+        let dump = unwrap_args(result).dump;
+        assert_eq!(dump.stdout, Show::Always);
+        assert_eq!(dump.stderr, Show::Never);
+        assert_eq!(dump.limit, Some(Limit::Lines(10, 20)));
+        assert_eq!(dump.context, Some(2));
+    }
+
+    #[test]
+    fn test_error_bad_dump_options() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "show",
+            "stdout",
+            "sometimes",
+            "command",
+            "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownShow)));
+
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "showlimit",
+            "words",
+            "1",
+            "1",
+            "command",
+            "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownLimit)));
+
+        let (_, result, _) = parse(args(vec!["prog", "showmatching", "-1", "command", "exe"]));
+        assert!(matches!(result, Err(ParseArgsError::BadCount)));
+    }
+
+    #[test]
+    fn test_redact_option() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "redact",
+            "literal",
+            "hunter2",
+            "redact",
+            "regex",
+            "token=\\S+",
+            "command",
+            "exe",
+        ]));
+        let redact = unwrap_args(result).redact;
+        assert_eq!(redact.len(), 2);
+        assert!(matches!(&redact[0], Matcher::Literal(_)));
+        assert!(matches!(&redact[1], Matcher::RegExp(_)));
+    }
+
+    #[test]
+    fn test_error_bad_extract_unit() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stdout", "extract", "(\\d+)", "", "", "n", "a;b", "command", "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::BadUnit)));
+
+        let (_, result, _) = parse(args(vec![
+            "prog", "stdout", "extract", "(\\d+)", "", "", "n",
+        ]));
+        assert!(matches!(
+            result,
+            Err(ParseArgsError::UnexpectedEnd(ExpectedArg::Command))
+        ));
+    }
+
+    #[test]
+    fn test_named_perfdata() {
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "exit",
+            "label=rc",
+            "crit=0:0",
+            "time",
+            "warn=",
+            "stdout",
+            "aggregate",
+            "sum",
+            "(\\d+)",
+            "uom=B",
+            "warn=@5",
+            "label=a=b",
+            "command",
+            "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.conditions.len(), 3);
+        match &parsed.conditions[0] {
+            Condition::ExitCode(pd) => {
+                assert!(pd.thresholds.warn.is_none());
+                assert!(pd.thresholds.crit.is_some());
+                assert_eq!(pd.label, "rc");
+            }
+            _ => panic!("expected ExitCode"),
+        }
+        assert!(matches!(
+            &parsed.conditions[1],
+            Condition::ExecSeconds(pd) if pd.label.is_empty() && pd.thresholds.warn.is_none()
+        ));
+        assert!(matches!(
+            &parsed.conditions[2],
+            Condition::Extract(_, Extraction { uom, .. }, pd)
+                if uom == "B" && pd.label == "a=b" && pd.thresholds.warn.is_some()
+        ));
+        assert_eq!(parsed.exe, OsString::from("exe"));
+    }
+
+    #[test]
+    fn test_error_bad_named_perfdata() {
+        let (_, result, position) = parse(args(vec![
+            "prog", "exit", "label=rc", "crit=x", "command", "exe",
+        ]));
+        assert!(matches!(
+            result,
+            Err(ParseArgsError::BadThreshold(ExpectedArg::Critical, _))
+        ));
+        assert_eq!(position, Position::Argument(3));
+
+        // uom= only makes sense for extract and aggregate
+        let (_, result, _) = parse(args(vec![
+            "prog", "exit", "uom=B", "", "", "command", "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::BadThreshold(..))));
+    }
+
+    #[test]
+    fn test_maxoutput_option() {
+        let (_, result, _) = parse(args(vec!["prog", "maxoutput", "1048576", "command", "exe"]));
+        assert_eq!(unwrap_args(result).max_output, Some(1048576));
+
+        let (_, result, _) = parse(args(vec!["prog", "maxoutput", "1MiB", "command", "exe"]));
+        assert!(matches!(result, Err(ParseArgsError::BadBytes)));
+    }
+
+    #[test]
+    fn test_stopon_option() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stopon", "stderr", "literal", "ready", "stopon", "stdout", "regex", "fail.*",
+            "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.stop_on.len(), 2);
+        assert!(matches!(
+            &parsed.stop_on[0],
+            StopOn {
+                stream: Stream::Stderr,
+                matcher: Matcher::Literal(lit),
+            } if lit == b"ready"
+        ));
+        assert!(matches!(
+            &parsed.stop_on[1],
+            StopOn {
+                stream: Stream::Stdout,
+                matcher: Matcher::RegExp(_),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_error_unknown_stopon_stream() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stopon", "stdin", "literal", "x", "command", "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownStream)));
+    }
+
     #[test]
     fn test_multiple_conditions() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog", "exit", "", "", "ec", "time", "", "", "t", "command", "exe",
         ]));
         let parsed = unwrap_args(result);
         assert_eq!(parsed.conditions.len(), 2);
     }
 
+    #[test]
+    fn test_env_options() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "cleanenv", "env", "LC_ALL", "C", "env", "EMPTY", "", "unenv", "HOME",
+            "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        assert_eq!(parsed.env.len(), 4);
+        assert!(matches!(parsed.env[0], EnvOp::Clear));
+        match &parsed.env[1] {
+            EnvOp::Set(name, value) => {
+                assert_eq!(name, "LC_ALL");
+                assert_eq!(value, "C");
+            }
+            _ => panic!("expected EnvOp::Set"),
+        }
+        assert!(matches!(&parsed.env[2], EnvOp::Set(_, value) if value.is_empty()));
+        assert!(matches!(&parsed.env[3], EnvOp::Unset(name) if name == "HOME"));
+    }
+
+    #[test]
+    fn test_error_empty_env_name() {
+        let (_, result, _) = parse(args(vec!["prog", "env", "", "x", "command", "exe"]));
+        assert!(matches!(
+            result,
+            Err(ParseArgsError::EmptyString(ExpectedArg::EnvName))
+        ));
+    }
+
+    #[test]
+    fn test_stdin_options() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "stdin", "literal", "data", "command", "exe",
+        ]));
+        assert!(matches!(
+            unwrap_args(result).stdin,
+            Some(Input::Literal(data)) if data == "data"
+        ));
+
+        let (_, result, _) = parse(args(vec![
+            "prog",
+            "stdin",
+            "literal",
+            "data",
+            "stdin",
+            "file",
+            "/etc/secret",
+            "command",
+            "exe",
+        ]));
+        assert!(matches!(
+            unwrap_args(result).stdin,
+            Some(Input::File(path)) if path == "/etc/secret"
+        ));
+
+        let (_, result, _) = parse(args(vec!["prog", "stdin", "null", "command", "exe"]));
+        assert!(matches!(unwrap_args(result).stdin, Some(Input::Null)));
+    }
+
+    #[test]
+    fn test_error_unknown_stdin() {
+        let (_, result, _) = parse(args(vec!["prog", "stdin", "pipe", "command", "exe"]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownInput)));
+    }
+
+    #[test]
+    fn test_timeout_option() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "timeout", "1.5", "critical", "command", "exe",
+        ]));
+        let parsed = unwrap_args(result);
+        match parsed.timeout {
+            Some(timeout) => {
+                assert_eq!(timeout.after, Duration::from_millis(1500));
+                assert!(timeout.state == State::Critical);
+            }
+            None => panic!("expected timeout"),
+        }
+    }
+
+    #[test]
+    fn test_error_bad_timeout_seconds() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "timeout", "-1", "critical", "command", "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::BadSeconds)));
+    }
+
+    #[test]
+    fn test_error_unknown_timeout_state() {
+        let (_, result, _) = parse(args(vec![
+            "prog", "timeout", "10", "fatal", "command", "exe",
+        ]));
+        assert!(matches!(result, Err(ParseArgsError::UnknownState)));
+    }
+
     #[test]
     fn test_error_unexpected_end_no_args() {
-        let (_, result, consumed) = parse_cmdline(args(vec!["prog"]));
+        let (_, result, consumed) = parse(args(vec!["prog"]));
         assert!(matches!(
             result,
             Err(ParseArgsError::UnexpectedEnd(ExpectedArg::Command))
         ));
-        assert_eq!(consumed, 0);
+        assert_eq!(consumed, Position::Argument(0));
     }
 
     #[test]
     fn test_error_unknown_param() {
-        let (_, result, _) = parse_cmdline(args(vec!["prog", "unknown"]));
+        let (_, result, _) = parse(args(vec!["prog", "unknown"]));
         assert!(matches!(result, Err(ParseArgsError::UnknownParam)));
     }
 
+    #[test]
+    fn test_every_param_is_parsed() {
+        for param in PARAMS {
+            let (_, result, _) = parse(args(vec!["prog", param.name]));
+            assert!(
+                !matches!(result, Err(ParseArgsError::UnknownParam)),
+                "{} is not parsed",
+                param.name
+            );
+        }
+    }
+
     #[test]
     fn test_error_bad_regex() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog", "stdout", "regex", "[invalid", "", "", "", "command", "exe",
         ]));
         assert!(matches!(result, Err(ParseArgsError::BadRegex(_))));
@@ -269,7 +1415,7 @@ mod tests {
 
     #[test]
     fn test_error_bad_threshold() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog",
             "exit",
             "not_a_range",
@@ -286,7 +1432,7 @@ mod tests {
 
     #[test]
     fn test_error_unknown_matcher() {
-        let (_, result, _) = parse_cmdline(args(vec![
+        let (_, result, _) = parse(args(vec![
             "prog",
             "stdout",
             "substring",
@@ -301,9 +1447,167 @@ mod tests {
     }
 
     #[test]
-    fn test_consumed_count_reflects_args_iterated() {
-        // 3 items consumed after program name: "command", "exe", "arg1"
-        let (_, _, consumed) = parse_cmdline(args(vec!["prog", "command", "exe", "arg1"]));
-        assert_eq!(consumed, 3);
+    fn test_choices_are_exactly_parsed() {
+        for (ea, before) in [
+            (ExpectedArg::Matcher, vec!["stdout"]),
+            (ExpectedArg::PatternKind, vec!["redact"]),
+            (ExpectedArg::State, vec!["timeout", "1"]),
+            (ExpectedArg::Input, vec!["stdin"]),
+            (ExpectedArg::Stream, vec!["show"]),
+            (ExpectedArg::Aggregate, vec!["stdout", "aggregate"]),
+            (ExpectedArg::Format, vec!["output"]),
+            (ExpectedArg::Show, vec!["show", "stdout"]),
+            (ExpectedArg::Limit, vec!["showlimit"]),
+        ] {
+            let at = Position::Argument(before.len() + 1);
+
+            for word in choices(&ea).unwrap().iter().chain(&["bogus"]) {
+                let mut cli = vec!["prog"];
+                cli.extend(&before);
+                cli.push(word);
+
+                // Running out of arguments after the word is fine
+                let (_, result, position) = parse(args(cli));
+                let rejected = position == at
+                    && matches!(result, Err(ref err) if !matches!(err, ParseArgsError::UnexpectedEnd(_)));
+                assert_eq!(rejected, *word == "bogus", "{:?}", word);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_unknown_pattern_kind() {
+        for param in ["stopon stdout", "redact", "stdout lines"] {
+            let mut cli = vec!["prog"];
+            cli.extend(param.split(' '));
+            cli.extend(["bytes", "x", "command", "exe"]);
+
+            let (_, result, _) = parse(args(cli));
+            assert!(
+                matches!(result, Err(ParseArgsError::UnknownPatternKind)),
+                "{}",
+                param
+            );
+        }
+    }
+
+    #[test]
+    fn test_error_position_counts_args() {
+        // "exe" is the 4th argument after the program name
+        let (_, _, position) = parse(args(vec!["prog", "exit", "", "", "exe"]));
+        assert_eq!(position, Position::Argument(4));
+    }
+
+    #[test]
+    fn test_all_errors_are_reported() {
+        let (_, result) = parse_cmdline(args(vec![
+            "prog", "exit", "", "x", "", "time", "", "", "", "foo", "bar", "stdout", "fuzzy",
+            "pattern", "", "", "", "output", "yaml", "command", "exe",
+        ]));
+
+        match result {
+            Err(ParseErrors { errors, .. }) => {
+                assert_eq!(errors.len(), 4);
+                assert!(matches!(
+                    errors[0],
+                    (
+                        ParseArgsError::BadThreshold(ExpectedArg::Critical, _),
+                        Position::Argument(3)
+                    )
+                ));
+                assert!(matches!(
+                    errors[1],
+                    (ParseArgsError::UnknownParam, Position::Argument(9))
+                ));
+                assert!(matches!(
+                    errors[2],
+                    (ParseArgsError::UnknownMatcher, Position::Argument(12))
+                ));
+                assert!(matches!(
+                    errors[3],
+                    (ParseArgsError::UnknownFormat, Position::Argument(18))
+                ));
+            }
+            Ok(_) => panic!("expected errors"),
+        }
+    }
+
+    #[test]
+    fn test_unexpected_end_is_reported_once() {
+        let (_, result) = parse_cmdline(args(vec!["prog", "foo", "exit", ""]));
+
+        match result {
+            Err(ParseErrors { errors, .. }) => {
+                assert_eq!(errors.len(), 2);
+                assert!(matches!(errors[0].0, ParseArgsError::UnknownParam));
+                assert!(matches!(
+                    errors[1].0,
+                    ParseArgsError::UnexpectedEnd(ExpectedArg::Critical)
+                ));
+            }
+            Ok(_) => panic!("expected errors"),
+        }
+    }
+
+    fn config(name: &str, toml: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "check_rungrep_{}_{}.toml",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, toml).unwrap();
+        path.into_os_string().into_string().unwrap()
+    }
+
+    #[test]
+    fn test_config_option() {
+        let path = config(
+            "ok",
+            "command = [\"exe\", \"arg1\"]\ncd = \"/tmp\"\n\n[[stdout]]\nkind = \"literal\"\npattern = \"x\"\n",
+        );
+        let (_, result, _) = parse(args(vec!["prog", "verbose", "config", path.clone().leak()]));
+        let (_, after, position) =
+            parse(args(vec!["prog", "config", path.clone().leak(), "ascii"]));
+        std::fs::remove_file(path).unwrap();
+
+        let parsed = unwrap_args(result);
+        assert!(parsed.verbose);
+        assert_eq!(parsed.cd, vec![OsString::from("/tmp")]);
+        assert_eq!(parsed.exe, OsString::from("exe"));
+        assert_eq!(parsed.args, vec![OsString::from("arg1")]);
+        assert!(matches!(
+            &parsed.conditions[0],
+            Condition::Stdout(Matcher::Literal(_), _)
+        ));
+
+        assert!(matches!(after, Err(ParseArgsError::AfterConfigCommand)));
+        assert_eq!(position, Position::Argument(3));
+    }
+
+    #[test]
+    fn test_error_bad_config() {
+        let path = config("bad", "[[stdout]]\nkind = \"regex\"\npattern = \"(\"\n");
+        let (_, result, position) = parse(args(vec![
+            "prog",
+            "config",
+            path.clone().leak(),
+            "command",
+            "exe",
+        ]));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ParseArgsError::BadRegex(_))));
+        assert_eq!(position, Position::Line(Rc::from(path.as_str()), 3));
+
+        let path = config("typo", "\n\ntime = { wrn = 1 }\n");
+        let (_, result, position) = parse(args(vec!["prog", "config", path.clone().leak()]));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ParseArgsError::BadConfig(_))));
+        assert_eq!(position, Position::Line(Rc::from(path.as_str()), 3));
+
+        let (_, result, position) = parse(args(vec!["prog", "config", "/nonexistent.toml"]));
+        assert!(matches!(result, Err(ParseArgsError::UnreadableConfig(_))));
+        assert_eq!(position, Position::Argument(2));
     }
 }
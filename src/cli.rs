@@ -1,22 +1,187 @@
-use crate::plugin::Perfdat;
+use crate::json_path::JsonPath;
+use crate::plugin::{Perfdat, State};
+use crate::template::Template;
 use regex::bytes::Regex;
 use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 use std::str::Utf8Error;
+use std::time::Duration;
+
+/// What the user asked for.
+pub(crate) enum Request {
+    Run(Box<Args>),
+    Validate(Box<Args>),
+    Help(Option<String>),
+    Version,
+}
 
 pub(crate) struct Args {
     pub(crate) cd: Vec<OsString>,
+    pub(crate) env: Vec<EnvOp>,
+    pub(crate) stdin: Option<Input>,
     pub(crate) exe: OsString,
     pub(crate) args: Vec<OsString>,
     pub(crate) conditions: Vec<Condition>,
+    pub(crate) timeout: Option<Timeout>,
+    pub(crate) max_output: Option<usize>,
+    pub(crate) stop_on: Vec<StopOn>,
+    pub(crate) passthrough: Option<String>,
+    pub(crate) format: Format,
+    pub(crate) ascii: bool,
+    pub(crate) summary: Option<Template>,
+    pub(crate) verbose: bool,
+    pub(crate) dump: Dump,
+    pub(crate) redact: Vec<Matcher>,
+}
+
+/// Which parts of the command's output to show.
+pub(crate) struct Dump {
+    pub(crate) stdout: Show,
+    pub(crate) stderr: Show,
+    pub(crate) limit: Option<Limit>,
+    pub(crate) context: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Show {
+    Never,
+    Problem,
+    Always,
+}
+
+/// How much to show from the beginning and the end.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Limit {
+    Lines(usize, usize),
+    Bytes(usize, usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Clone)]
+pub(crate) struct StopOn {
+    pub(crate) stream: Stream,
+    pub(crate) matcher: Matcher,
+}
+
+pub(crate) enum EnvOp {
+    Set(OsString, OsString),
+    Unset(OsString),
+    Clear,
+}
+
+pub(crate) enum Input {
+    Literal(OsString),
+    File(OsString),
+    Null,
+}
+
+pub(crate) struct Timeout {
+    pub(crate) after: Duration,
+    pub(crate) state: State,
 }
 
 pub(crate) enum Condition {
     ExecSeconds(Perfdat),
     ExitCode(Perfdat),
+    Signal(Perfdat),
+    Leftovers(Perfdat),
+    Usage(Resource, Perfdat),
     Stdout(Matcher, Perfdat),
     Stderr(Matcher, Perfdat),
+    Bytes(Stream, Perfdat),
+    Lines(Stream, Lines, Matcher, Perfdat),
+    Extract(Stream, Extraction, Perfdat),
+    Json(Stream, JsonPath, Perfdat),
 }
 
+/// A regular expression capturing numbers, their unit of measurement
+/// and how to aggregate them.
+#[derive(Clone)]
+pub(crate) struct Extraction {
+    pub(crate) regex: Regex,
+    pub(crate) uom: String,
+    pub(crate) aggregate: Aggregate,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Aggregate {
+    Sum,
+    Min,
+    Max,
+    Avg,
+    Last,
+    Each,
+}
+
+impl Aggregate {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Aggregate::Sum => "sum",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+            Aggregate::Avg => "avg",
+            Aggregate::Last => "last",
+            Aggregate::Each => "each",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Lines {
+    Matching,
+    NotMatching,
+}
+
+impl Lines {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Lines::Matching => "lines",
+            Lines::NotMatching => "inverted",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Resource {
+    CpuTime,
+    MaxRss,
+    MajorFaults,
+    ContextSwitches,
+}
+
+impl Resource {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Resource::CpuTime => "cputime",
+            Resource::MaxRss => "maxrss",
+            Resource::MajorFaults => "majflt",
+            Resource::ContextSwitches => "ctxsw",
+        }
+    }
+}
+
+#[derive(Clone)]
 pub(crate) enum Matcher {
     Literal(Vec<u8>),
     RegExp(Regex),
@@ -29,12 +194,55 @@ pub(crate) enum ParseArgsError {
     UnknownParam,
     BadThreshold(ExpectedArg, nagios_range::Error),
     UnknownMatcher,
+    UnknownPatternKind,
     BadRegex(regex::Error),
+    BadSeconds,
+    UnknownState,
+    UnknownInput,
+    BadBytes,
+    UnknownStream,
+    BadUnit,
+    UnknownAggregate,
+    BadJsonPath,
+    UnknownFormat,
+    BadTemplate,
+    UnknownShow,
+    UnknownLimit,
+    BadCount,
+    UnreadableConfig(std::io::Error),
+    BadConfig(String),
+    AfterConfigCommand,
+}
+
+/// All errors in the arguments and where each one is,
+/// along with what could be parsed anyway.
+pub(crate) struct ParseErrors {
+    pub(crate) errors: Vec<(ParseArgsError, Position)>,
+    pub(crate) partial: Box<Args>,
+}
+
+/// Where an argument came from.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Position {
+    /// The N-th CLI argument.
+    Argument(usize),
+    /// A line of a configuration file.
+    Line(Rc<str>, usize),
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Position::Argument(n) => write!(f, "CLI argument #{}", n),
+            Position::Line(file, line) => write!(f, "{}:{}", file, line),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub(crate) enum ExpectedArg {
     Matcher,
+    PatternKind,
     Pattern,
     Warning,
     Critical,
@@ -42,4 +250,23 @@ pub(crate) enum ExpectedArg {
     Command,
     WorkDir,
     Exe,
+    Seconds,
+    State,
+    EnvName,
+    EnvValue,
+    Input,
+    InputFile,
+    InputData,
+    Bytes,
+    Stream,
+    Unit,
+    Aggregate,
+    JsonPath,
+    Prefix,
+    Format,
+    Template,
+    Show,
+    Limit,
+    Count,
+    ConfigFile,
 }
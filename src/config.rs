@@ -0,0 +1,446 @@
+use std::ffi::OsString;
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+/// An argument translated from a configuration file and its line number.
+pub(crate) type Argument = (OsString, usize);
+
+/// What went wrong where in a configuration file.
+pub(crate) type Error = (String, usize);
+
+/// Fields which default to an empty string.
+const OPTIONAL: [&str; 4] = ["warn", "crit", "label", "uom"];
+
+/// Translates a TOML configuration file into the CLI arguments it stands for,
+/// so that they are validated just like CLI arguments.
+/// The command (if any) comes last, wherever it is in the file.
+pub(crate) fn translate(source: &str) -> Result<Vec<Argument>, Error> {
+    let document = ImDocument::parse(source)
+        .map_err(|err| (err.message().trim().to_owned(), line(source, err.span())))?;
+    let mut translator = Translator {
+        source,
+        arguments: Vec::new(),
+    };
+    let mut command = Vec::new();
+
+    for (param, item) in document.iter() {
+        let at = translator.line(item.span());
+
+        match param {
+            "command" => {
+                command.push((OsString::from(param), at));
+
+                for value in match item {
+                    Item::Value(Value::Array(array)) => array.iter().collect(),
+                    Item::Value(value) => vec![value],
+                    _ => Vec::new(),
+                } {
+                    command.push(translator.scalar(value)?);
+                }
+
+                if command.len() < 2 {
+                    return Err(("Expected a non-empty command".to_owned(), at));
+                }
+            }
+            "ascii" | "verbose" | "cleanenv" => match item.as_bool() {
+                None => return Err((format!("Expected true or false for \"{}\"", param), at)),
+                Some(true) => translator.push(param, at),
+                Some(false) => {}
+            },
+            "env" | "show" => {
+                let table = item
+                    .as_table_like()
+                    .ok_or_else(|| (format!("Expected a table for \"{}\"", param), at))?;
+
+                for (key, value) in table.iter() {
+                    let value = translator.item(value, at)?;
+
+                    translator.push(param, value.1);
+                    translator.push(key, value.1);
+                    translator.arguments.push(value);
+                }
+            }
+            "cd" | "unenv" | "maxoutput" | "passthrough" | "output" | "summary"
+            | "showmatching" => {
+                for entry in entries(item) {
+                    match entry {
+                        Entry::Table(..) => {
+                            return Err((
+                                format!("Expected a string or number for \"{}\"", param),
+                                at,
+                            ))
+                        }
+                        Entry::Value(value) => {
+                            let value = translator.scalar(value)?;
+
+                            translator.push(param, value.1);
+                            translator.arguments.push(value);
+                        }
+                    }
+                }
+            }
+            _ => {
+                let head = fields(param)
+                    .ok_or_else(|| (format!("Unknown parameter \"{}\"", param), at))?;
+
+                for entry in entries(item) {
+                    match entry {
+                        Entry::Value(_) => {
+                            return Err((format!("Expected a table for \"{}\"", param), at))
+                        }
+                        Entry::Table(table, span) => {
+                            let at = translator.line(span).max(at);
+                            translator.record(param, head, table, at)?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    translator.arguments.extend(command);
+    Ok(translator.arguments)
+}
+
+struct Translator<'a> {
+    source: &'a str,
+    arguments: Vec<Argument>,
+}
+
+impl Translator<'_> {
+    fn push(&mut self, argument: &str, at: usize) {
+        self.arguments.push((OsString::from(argument), at));
+    }
+
+    /// Translates one occurrence of a parameter taking named fields.
+    fn record(
+        &mut self,
+        param: &str,
+        head: &[&str],
+        table: &dyn TableLike,
+        at: usize,
+    ) -> Result<(), Error> {
+        let mut known = head.to_vec();
+
+        self.push(param, at);
+        self.fields(param, head, table, at)?;
+
+        if head.last() == Some(&"kind") {
+            let kind = table.get("kind").and_then(Item::as_str).unwrap_or_default();
+
+            match kind_fields(param, kind) {
+                // The parser will complain about the kind
+                None => return Ok(()),
+                Some(tail) => {
+                    self.fields(param, tail, table, at)?;
+                    known.extend_from_slice(tail);
+                }
+            }
+        }
+
+        for (key, value) in table.iter() {
+            if !known.contains(&key) {
+                return Err((
+                    format!("Unknown field \"{}\" of \"{}\"", key, param),
+                    self.line(value.span()).max(at),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fields(
+        &mut self,
+        param: &str,
+        fields: &[&str],
+        table: &dyn TableLike,
+        at: usize,
+    ) -> Result<(), Error> {
+        for field in fields {
+            match table.get(field) {
+                None if OPTIONAL.contains(field) => self.push("", at),
+                None => return Err((format!("Missing field \"{}\" of \"{}\"", field, param), at)),
+                Some(value) => {
+                    let value = self.item(value, at)?;
+                    self.arguments.push(value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn item(&self, item: &Item, at: usize) -> Result<Argument, Error> {
+        match item {
+            Item::Value(value) => self.scalar(value),
+            _ => Err(("Expected a string or number".to_owned(), at)),
+        }
+    }
+
+    fn scalar(&self, value: &Value) -> Result<Argument, Error> {
+        let at = self.line(value.span());
+
+        match value {
+            Value::String(string) => Ok((OsString::from(string.value()), at)),
+            Value::Integer(integer) => Ok((OsString::from(integer.value().to_string()), at)),
+            Value::Float(float) => Ok((OsString::from(float.value().to_string()), at)),
+            _ => Err(("Expected a string or number".to_owned(), at)),
+        }
+    }
+
+    fn line(&self, span: Option<Range<usize>>) -> usize {
+        line(self.source, span)
+    }
+}
+
+/// Returns the 1-based number of the line a span starts at.
+fn line(source: &str, span: Option<Range<usize>>) -> usize {
+    match span {
+        None => 0,
+        Some(span) => {
+            source.as_bytes()[..span.start.min(source.len())]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count()
+                + 1
+        }
+    }
+}
+
+/// One occurrence of a parameter. Arrays stand for repeating it.
+enum Entry<'a> {
+    Value(&'a Value),
+    Table(&'a dyn TableLike, Option<Range<usize>>),
+}
+
+fn entries<'a>(item: &'a Item) -> Vec<Entry<'a>> {
+    let entry = |value: &'a Value| match value {
+        Value::InlineTable(table) => Entry::Table(table, table.span()),
+        _ => Entry::Value(value),
+    };
+
+    match item {
+        Item::None => Vec::new(),
+        Item::Value(Value::Array(array)) => array.iter().map(entry).collect(),
+        Item::Value(value) => vec![entry(value)],
+        Item::Table(table) => vec![Entry::Table(table, table.span())],
+        Item::ArrayOfTables(tables) => tables
+            .iter()
+            .map(|table| Entry::Table(table, table.span()))
+            .collect(),
+    }
+}
+
+/// The fields of a parameter in the order of its CLI arguments.
+/// If the last one is "kind", more follow depending on its value.
+fn fields(param: &str) -> Option<&'static [&'static str]> {
+    match param {
+        "time" | "exit" | "signal" | "leftovers" | "cputime" | "maxrss" | "majflt" | "ctxsw" => {
+            Some(&["warn", "crit", "label"])
+        }
+        "stdout" | "stderr" | "stdin" | "showlimit" | "redact" => Some(&["kind"]),
+        "stopon" => Some(&["stream", "kind"]),
+        "timeout" => Some(&["seconds", "state"]),
+        _ => None,
+    }
+}
+
+fn kind_fields(param: &str, kind: &str) -> Option<&'static [&'static str]> {
+    match (param, kind) {
+        ("stdout" | "stderr", "literal" | "regex") => Some(&["pattern", "warn", "crit", "label"]),
+        ("stdout" | "stderr", "lines" | "inverted") => {
+            Some(&["match", "pattern", "warn", "crit", "label"])
+        }
+        ("stdout" | "stderr", "bytes") => Some(&["warn", "crit", "label"]),
+        ("stdout" | "stderr", "extract") => Some(&["regex", "warn", "crit", "label", "uom"]),
+        ("stdout" | "stderr", "aggregate") => {
+            Some(&["function", "regex", "warn", "crit", "label", "uom"])
+        }
+        ("stdout" | "stderr", "json") => Some(&["path", "warn", "crit", "label"]),
+        ("stdin", "literal") => Some(&["data"]),
+        ("stdin", "file") => Some(&["path"]),
+        ("stdin", "null") => Some(&[]),
+        ("stopon" | "redact", "literal" | "regex") => Some(&["pattern"]),
+        ("showlimit", "lines" | "bytes") => Some(&["head", "tail"]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{choices, Slot, PARAMS};
+
+    fn arguments(source: &str) -> Result<Vec<(String, usize)>, Error> {
+        translate(source).map(|arguments| {
+            arguments
+                .into_iter()
+                .map(|(argument, line)| (argument.into_string().unwrap(), line))
+                .collect()
+        })
+    }
+
+    fn words(source: &str) -> Vec<String> {
+        arguments(source)
+            .unwrap()
+            .into_iter()
+            .map(|(argument, _)| argument)
+            .collect()
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            words(
+                r#"
+command = ["zpool", "status", "-x"]
+cd = "/tmp"
+env = { LC_ALL = "C" }
+ascii = true
+verbose = false
+timeout = { seconds = 10, state = "critical" }
+showmatching = 2
+
+[[stdout]]
+kind = "literal"
+pattern = "all pools are healthy"
+crit = "1:"
+
+[[stdout]]
+kind = "aggregate"
+function = "max"
+regex = '(\d+)%'
+warn = 80
+label = "cap"
+uom = "%"
+"#
+            ),
+            [
+                "cd",
+                "/tmp",
+                "env",
+                "LC_ALL",
+                "C",
+                "ascii",
+                "timeout",
+                "10",
+                "critical",
+                "showmatching",
+                "2",
+                "stdout",
+                "literal",
+                "all pools are healthy",
+                "",
+                "1:",
+                "",
+                "stdout",
+                "aggregate",
+                "max",
+                r"(\d+)%",
+                "80",
+                "",
+                "cap",
+                "%",
+                "command",
+                "zpool",
+                "status",
+                "-x"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_translate_repetitions() {
+        assert_eq!(
+            words(
+                r#"
+cd = ["/a", "b"]
+redact = [{ kind = "literal", pattern = "pw" }, { kind = "regex", pattern = "t=\\w+" }]
+"#
+            ),
+            ["cd", "/a", "cd", "b", "redact", "literal", "pw", "redact", "regex", r"t=\w+"]
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let arguments =
+            arguments("exit = { crit = \"0\" }\n\n[[stderr]]\nkind = \"bytes\"\nlabel = \"x\"\n")
+                .unwrap();
+
+        assert_eq!(arguments[0], ("exit".to_owned(), 1));
+        assert_eq!(arguments[2], ("0".to_owned(), 1));
+        assert_eq!(arguments[4], ("stderr".to_owned(), 3));
+        assert_eq!(arguments[5], ("bytes".to_owned(), 4));
+        assert_eq!(arguments[8], ("x".to_owned(), 5));
+    }
+
+    #[test]
+    fn test_unknown_kind_is_left_to_the_parser() {
+        assert_eq!(
+            words("stdout = { kind = \"fuzzy\", pattern = \"x\" }"),
+            ["stdout", "fuzzy"]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source| arguments(source).unwrap_err();
+
+        assert_eq!(error("\n\nstdout = ").1, 3);
+        assert_eq!(
+            error("foo = 1"),
+            ("Unknown parameter \"foo\"".to_owned(), 1)
+        );
+        assert_eq!(
+            error("\n[[stdout]]\nkind = \"regex\"\ncrti = \"1\"\npattern = \"x\""),
+            ("Unknown field \"crti\" of \"stdout\"".to_owned(), 4)
+        );
+        assert_eq!(
+            error("stdout = { kind = \"regex\" }"),
+            ("Missing field \"pattern\" of \"stdout\"".to_owned(), 1)
+        );
+        assert_eq!(
+            error("ascii = \"yes\""),
+            ("Expected true or false for \"ascii\"".to_owned(), 1)
+        );
+        assert_eq!(
+            error("cd = true"),
+            ("Expected a string or number".to_owned(), 1)
+        );
+        assert_eq!(error("command = []").1, 1);
+        assert_eq!(error("time = \"1\"").1, 1);
+    }
+
+    #[test]
+    fn test_kinds_match_grammar() {
+        let stdout = PARAMS.iter().find(|param| param.name == "stdout").unwrap();
+
+        for form in stdout.forms {
+            let kinds = match &form[0] {
+                Slot::Words(words) => *words,
+                Slot::Choice(ea) => choices(ea).unwrap(),
+                _ => panic!("stdout form without kind"),
+            };
+            let fields: Vec<_> = form[1..]
+                .iter()
+                .map(|slot| match slot {
+                    Slot::Arg(placeholder, _) | Slot::Optional(placeholder, _) => {
+                        placeholder.to_lowercase()
+                    }
+                    _ => "match".to_owned(),
+                })
+                .collect();
+
+            for kind in kinds {
+                assert_eq!(
+                    kind_fields("stdout", kind).map(|fields| fields.to_vec()),
+                    Some(fields.iter().map(String::as_str).collect()),
+                    "{}",
+                    kind
+                );
+            }
+        }
+    }
+}
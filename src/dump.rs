@@ -0,0 +1,147 @@
+use crate::cli::{Limit, Matcher};
+use crate::scan::matches;
+
+/// Returns the part of a stream to show: only the lines matching any of the patterns
+/// (if context is given) plus that many lines around them, then only the head and tail
+/// (if limited).
+pub(crate) fn excerpt(
+    data: &[u8],
+    context: Option<usize>,
+    patterns: &[Matcher],
+    limit: Option<Limit>,
+) -> Vec<u8> {
+    let data = match context {
+        None => data.to_vec(),
+        Some(context) => matching(data, context, patterns),
+    };
+
+    match limit {
+        None => data,
+        Some(Limit::Lines(head, tail)) => {
+            let lines: Vec<_> = data.split_inclusive(|&b| b == b'\n').collect();
+
+            if lines.len() <= head + tail {
+                return data;
+            }
+
+            let mut excerpt = lines[..head].concat();
+
+            terminate(&mut excerpt);
+            excerpt.extend_from_slice(
+                format!("[... {} lines omitted ...]\n", lines.len() - head - tail).as_bytes(),
+            );
+            excerpt.extend_from_slice(&lines[lines.len() - tail..].concat());
+            excerpt
+        }
+        Some(Limit::Bytes(head, tail)) => {
+            if data.len() <= head + tail {
+                return data;
+            }
+
+            let mut excerpt = data[..head].to_vec();
+
+            terminate(&mut excerpt);
+            excerpt.extend_from_slice(
+                format!("[... {} bytes omitted ...]\n", data.len() - head - tail).as_bytes(),
+            );
+            excerpt.extend_from_slice(&data[data.len() - tail..]);
+            excerpt
+        }
+    }
+}
+
+/// Like grep -C: matching lines with context, groups separated by "--".
+fn matching(data: &[u8], context: usize, patterns: &[Matcher]) -> Vec<u8> {
+    let lines: Vec<_> = data.split_inclusive(|&b| b == b'\n').collect();
+    let mut keep = vec![false; lines.len()];
+
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+
+        if patterns.iter().any(|pattern| matches(pattern, line)) {
+            for kept in &mut keep[i.saturating_sub(context)..lines.len().min(i + context + 1)] {
+                *kept = true;
+            }
+        }
+    }
+
+    let mut excerpt = Vec::new();
+    let mut gap = false;
+
+    for (line, kept) in lines.iter().zip(keep) {
+        if kept {
+            if gap && !excerpt.is_empty() {
+                excerpt.extend_from_slice(b"--\n");
+            }
+
+            excerpt.extend_from_slice(line);
+            terminate(&mut excerpt);
+            gap = false;
+        } else {
+            gap = true;
+        }
+    }
+
+    excerpt
+}
+
+/// Makes sure a non-empty text ends with a newline.
+fn terminate(text: &mut Vec<u8>) {
+    if text.last().is_some_and(|&b| b != b'\n') {
+        text.push(b'\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::bytes::Regex;
+
+    const DATA: &[u8] = b"1\n2\nERROR a\n4\n5\n6\n7\nERROR b\n9";
+
+    #[test]
+    fn test_unlimited() {
+        assert_eq!(excerpt(DATA, None, &[], None), DATA);
+    }
+
+    #[test]
+    fn test_head_tail_lines() {
+        assert_eq!(
+            excerpt(DATA, None, &[], Some(Limit::Lines(2, 1))),
+            b"1\n2\n[... 6 lines omitted ...]\n9"
+        );
+        assert_eq!(excerpt(DATA, None, &[], Some(Limit::Lines(5, 4))), DATA);
+    }
+
+    #[test]
+    fn test_head_tail_bytes() {
+        assert_eq!(
+            excerpt(b"abcdefgh\n", None, &[], Some(Limit::Bytes(3, 0))),
+            b"abc\n[... 6 bytes omitted ...]\n"
+        );
+    }
+
+    #[test]
+    fn test_matching_with_context() {
+        let patterns = [Matcher::Literal(b"ERROR".to_vec())];
+
+        assert_eq!(
+            excerpt(DATA, Some(1), &patterns, None),
+            b"2\nERROR a\n4\n--\n7\nERROR b\n9\n"
+        );
+        assert_eq!(
+            excerpt(DATA, Some(0), &patterns, Some(Limit::Lines(1, 0))),
+            b"ERROR a\n[... 2 lines omitted ...]\n"
+        );
+    }
+
+    #[test]
+    fn test_matching_merges_overlapping_context() {
+        let patterns = [Matcher::RegExp(Regex::new("^[46]$").unwrap())];
+
+        assert_eq!(
+            excerpt(DATA, Some(1), &patterns, None),
+            b"ERROR a\n4\n5\n6\n7\n"
+        );
+    }
+}
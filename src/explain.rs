@@ -0,0 +1,277 @@
+use crate::cli::{Aggregate, Args, Condition, Lines, Matcher, Resource};
+use crate::plugin::Perfdat;
+use nagios_range::NagiosRange;
+use std::fmt::Write;
+
+/// Describes in plain words what a command line would do.
+pub(crate) fn explain(args: &Args) -> String {
+    let mut explanation = String::new();
+    let mut command = args.exe.to_string_lossy().into_owned();
+
+    for arg in &args.args {
+        command.push(' ');
+        command.push_str(&arg.to_string_lossy());
+    }
+
+    let _ = writeln!(explanation, "Would run: {}", command);
+
+    match &args.timeout {
+        None => {}
+        Some(timeout) => {
+            let _ = writeln!(
+                explanation,
+                "Terminating it after {} seconds as {}.",
+                timeout.after.as_secs_f64(),
+                timeout.state.name().to_uppercase()
+            );
+        }
+    }
+
+    match &args.passthrough {
+        None => {}
+        Some(_) => explanation.push_str("Reporting its exit code and output as a plugin's.\n"),
+    }
+
+    if args.conditions.is_empty() {
+        explanation.push_str("Checking nothing else.\n");
+    }
+
+    for (i, condition) in args.conditions.iter().enumerate() {
+        let (what, perfdata) = describe(condition);
+
+        let _ = writeln!(explanation, "{}. {}.", i + 1, what);
+        let _ = writeln!(explanation, "   {}", consequences(perfdata));
+    }
+
+    explanation
+}
+
+/// Returns what a condition measures and how it's judged.
+fn describe(condition: &Condition) -> (String, &Perfdat) {
+    match condition {
+        Condition::ExecSeconds(perfdata) => {
+            ("The command's run time in seconds".to_owned(), perfdata)
+        }
+        Condition::ExitCode(perfdata) => (
+            "The command's exit code, unless it's killed".to_owned(),
+            perfdata,
+        ),
+        Condition::Signal(perfdata) => (
+            "The signal which killed the command, 0 if none".to_owned(),
+            perfdata,
+        ),
+        Condition::Leftovers(perfdata) => (
+            "The number of the command's processes left running".to_owned(),
+            perfdata,
+        ),
+        Condition::Usage(resource, perfdata) => (
+            match resource {
+                Resource::CpuTime => "The command's CPU time in seconds",
+                Resource::MaxRss => "The command's maximum resident set size in bytes",
+                Resource::MajorFaults => "The number of the command's major page faults",
+                Resource::ContextSwitches => "The number of the command's context switches",
+            }
+            .to_owned(),
+            perfdata,
+        ),
+        Condition::Stdout(matcher, perfdata) => (
+            format!("How often stdout contains {}", matcher_text(matcher)),
+            perfdata,
+        ),
+        Condition::Stderr(matcher, perfdata) => (
+            format!("How often stderr contains {}", matcher_text(matcher)),
+            perfdata,
+        ),
+        Condition::Bytes(stream, perfdata) => {
+            (format!("The size of {} in bytes", stream.name()), perfdata)
+        }
+        Condition::Lines(stream, lines, matcher, perfdata) => (
+            format!(
+                "The number of {} lines {} {}",
+                stream.name(),
+                match lines {
+                    Lines::Matching => "matching",
+                    Lines::NotMatching => "not matching",
+                },
+                matcher_text(matcher)
+            ),
+            perfdata,
+        ),
+        Condition::Extract(stream, extraction, perfdata) => {
+            let numbers = format!(
+                "in {} captured by regular expression \"{}\"",
+                stream.name(),
+                extraction.regex
+            );
+            let mut what = match extraction.aggregate {
+                Aggregate::Each => format!(
+                    "Each number {}, labelled by the group named \"label\"",
+                    numbers
+                ),
+                aggregate => format!(
+                    "The {} of the numbers {}",
+                    match aggregate {
+                        Aggregate::Sum => "sum",
+                        Aggregate::Min => "minimum",
+                        Aggregate::Max => "maximum",
+                        Aggregate::Avg => "average",
+                        _ => "last",
+                    },
+                    numbers
+                ),
+            };
+
+            if !extraction.uom.is_empty() {
+                let _ = write!(what, ", in {}", extraction.uom);
+            }
+
+            (what, perfdata)
+        }
+        Condition::Json(stream, path, perfdata) => (
+            format!(
+                "The number at {} in {} parsed as JSON, or how many values it refers to",
+                path,
+                stream.name()
+            ),
+            perfdata,
+        ),
+    }
+}
+
+fn matcher_text(matcher: &Matcher) -> String {
+    match matcher {
+        Matcher::Literal(literal) => {
+            format!("literal string \"{}\"", String::from_utf8_lossy(literal))
+        }
+        Matcher::RegExp(regexp) => format!("regular expression \"{}\"", regexp),
+    }
+}
+
+/// Describes which values are alerted about and how they're reported.
+fn consequences(perfdata: &Perfdat) -> String {
+    let mut consequences = String::new();
+
+    for (state, range) in [
+        ("Warning", &perfdata.thresholds.warn),
+        ("Critical", &perfdata.thresholds.crit),
+    ] {
+        match range {
+            None => {}
+            Some(range) => {
+                let _ = write!(consequences, "{} ({}). ", alerting(state, range), range);
+            }
+        }
+    }
+
+    match perfdata.label.is_empty() {
+        true => consequences.push_str("Not reported as perfdata."),
+        false => {
+            let _ = write!(consequences, "Reported as perfdata '{}'.", perfdata.label);
+        }
+    }
+
+    consequences
+}
+
+fn alerting(state: &str, range: &NagiosRange) -> String {
+    let start = (!range.start_is_infinite()).then(|| range.start());
+    let end = (!range.end_is_infinite()).then(|| range.end());
+
+    match (range.checks_inside(), start, end) {
+        (false, None, None) => format!("Never {}", state.to_lowercase()),
+        (false, Some(start), None) => format!("{} if below {}", state, start),
+        (false, None, Some(end)) => format!("{} if above {}", state, end),
+        (false, Some(start), Some(end)) if start == end => {
+            format!("{} unless {}", state, start)
+        }
+        (false, Some(start), Some(end)) => {
+            format!("{} if below {} or above {}", state, start, end)
+        }
+        (true, None, None) => format!("Always {}", state.to_lowercase()),
+        (true, Some(start), None) => format!("{} if at least {}", state, start),
+        (true, None, Some(end)) => format!("{} if at most {}", state, end),
+        (true, Some(start), Some(end)) => {
+            format!("{} if between {} and {}", state, start, end)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arg_parser::parse_cmdline;
+    use crate::cli::Request;
+    use std::ffi::OsString;
+
+    fn explained(args: &[&str]) -> String {
+        let (_, result) =
+            parse_cmdline(["prog", "validate"].iter().chain(args).map(OsString::from));
+
+        match result {
+            Ok(Request::Validate(args)) => explain(&args),
+            _ => panic!("expected a validation"),
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(
+            explained(&[
+                "timeout",
+                "2.5",
+                "critical",
+                "exit",
+                "",
+                "0:0",
+                "rc",
+                "stdout",
+                "lines",
+                "regex",
+                "^ERR",
+                "@1:5",
+                "10",
+                "",
+                "stderr",
+                "aggregate",
+                "avg",
+                "took (\\d+)ms",
+                "~:100",
+                "",
+                "latency",
+                "ms",
+                "command",
+                "sh",
+                "-c",
+                "true",
+            ]),
+            "Would run: sh -c true
+Terminating it after 2.5 seconds as CRITICAL.
+1. The command's exit code, unless it's killed.
+   Critical unless 0 (0:0). Reported as perfdata 'rc'.
+2. The number of stdout lines matching regular expression \"^ERR\".
+   Warning if between 1 and 5 (@1:5). Critical if below 0 or above 10 (0:10). Not reported as perfdata.
+3. The average of the numbers in stderr captured by regular expression \"took (\\d+)ms\", in ms.
+   Warning if above 100 (~:100). Reported as perfdata 'latency'.
+"
+        );
+    }
+
+    #[test]
+    fn test_explain_nothing() {
+        assert_eq!(
+            explained(&["command", "true"]),
+            "Would run: true\nChecking nothing else.\n"
+        );
+    }
+
+    #[test]
+    fn test_alerting() {
+        let alerting = |range| alerting("Critical", &NagiosRange::from(range).unwrap());
+
+        assert_eq!(alerting("10"), "Critical if below 0 or above 10");
+        assert_eq!(alerting("1:"), "Critical if below 1");
+        assert_eq!(alerting("~:"), "Never critical");
+        assert_eq!(alerting("@~:"), "Always critical");
+        assert_eq!(alerting("@~:3"), "Critical if at most 3");
+    }
+}
@@ -0,0 +1,425 @@
+use crate::cli::ExpectedArg;
+
+/// A parameter and the arguments it takes.
+pub(crate) struct Param {
+    pub(crate) name: &'static str,
+    /// Alternative sequences of arguments.
+    pub(crate) forms: &'static [&'static [Slot]],
+    pub(crate) about: &'static str,
+}
+
+pub(crate) enum Slot {
+    /// Literally one of these words.
+    Words(&'static [&'static str]),
+    /// Literally one of the words such an argument may be.
+    Choice(ExpectedArg),
+    /// An argument shown as the given placeholder.
+    Arg(&'static str, ExpectedArg),
+    /// Like `Arg`, but left out if followed by the next parameter.
+    Optional(&'static str, ExpectedArg),
+    /// All remaining arguments.
+    Rest,
+}
+
+const WARN: Slot = Slot::Arg("WARN", ExpectedArg::Warning);
+const CRIT: Slot = Slot::Arg("CRIT", ExpectedArg::Critical);
+const LABEL: Slot = Slot::Arg("LABEL", ExpectedArg::Label);
+const PATTERN: Slot = Slot::Arg("PATTERN", ExpectedArg::Pattern);
+const MATCHER: Slot = Slot::Choice(ExpectedArg::PatternKind);
+const PERFDATA: &[&[Slot]] = &[&[WARN, CRIT, LABEL]];
+const STREAM: &[&[Slot]] = &[
+    &[MATCHER, PATTERN, WARN, CRIT, LABEL],
+    &[
+        Slot::Words(&["lines", "inverted"]),
+        MATCHER,
+        PATTERN,
+        WARN,
+        CRIT,
+        LABEL,
+    ],
+    &[Slot::Words(&["bytes"]), WARN, CRIT, LABEL],
+    &[
+        Slot::Words(&["extract"]),
+        Slot::Arg("REGEX", ExpectedArg::Pattern),
+        WARN,
+        CRIT,
+        LABEL,
+        Slot::Optional("UOM", ExpectedArg::Unit),
+    ],
+    &[
+        Slot::Words(&["aggregate"]),
+        Slot::Arg("FUNCTION", ExpectedArg::Aggregate),
+        Slot::Arg("REGEX", ExpectedArg::Pattern),
+        WARN,
+        CRIT,
+        LABEL,
+        Slot::Optional("UOM", ExpectedArg::Unit),
+    ],
+    &[
+        Slot::Words(&["json"]),
+        Slot::Arg("PATH", ExpectedArg::JsonPath),
+        WARN,
+        CRIT,
+        LABEL,
+    ],
+];
+
+/// All parameters in the order they're documented.
+pub(crate) const PARAMS: &[Param] = &[
+    Param {
+        name: "command",
+        forms: &[&[Slot::Arg("EXE", ExpectedArg::Exe), Slot::Rest]],
+        about: "Run EXE with ARGS. Required and always last.",
+    },
+    Param {
+        name: "config",
+        forms: &[&[Slot::Arg("PATH", ExpectedArg::ConfigFile)]],
+        about: "Read parameters from a TOML file.",
+    },
+    Param {
+        name: "cd",
+        forms: &[&[Slot::Arg("DIR", ExpectedArg::WorkDir)]],
+        about: "Change the working directory.",
+    },
+    Param {
+        name: "env",
+        forms: &[&[
+            Slot::Arg("NAME", ExpectedArg::EnvName),
+            Slot::Arg("VALUE", ExpectedArg::EnvValue),
+        ]],
+        about: "Set an environment variable for the command.",
+    },
+    Param {
+        name: "unenv",
+        forms: &[&[Slot::Arg("NAME", ExpectedArg::EnvName)]],
+        about: "Remove an environment variable for the command.",
+    },
+    Param {
+        name: "cleanenv",
+        forms: &[&[]],
+        about: "Remove all environment variables for the command.",
+    },
+    Param {
+        name: "stdin",
+        forms: &[
+            &[
+                Slot::Words(&["literal"]),
+                Slot::Arg("DATA", ExpectedArg::InputData),
+            ],
+            &[
+                Slot::Words(&["file"]),
+                Slot::Arg("PATH", ExpectedArg::InputFile),
+            ],
+            &[Slot::Words(&["null"])],
+        ],
+        about: "Feed the command's stdin.",
+    },
+    Param {
+        name: "timeout",
+        forms: &[&[
+            Slot::Arg("SECONDS", ExpectedArg::Seconds),
+            Slot::Arg("STATE", ExpectedArg::State),
+        ]],
+        about: "Terminate the command after SECONDS and report STATE.",
+    },
+    Param {
+        name: "maxoutput",
+        forms: &[&[Slot::Arg("BYTES", ExpectedArg::Bytes)]],
+        about: "Keep at most BYTES of each of the command's stdout and stderr for display.",
+    },
+    Param {
+        name: "stopon",
+        forms: &[&[Slot::Arg("STREAM", ExpectedArg::Stream), MATCHER, PATTERN]],
+        about: "Terminate the command once STREAM contains PATTERN.",
+    },
+    Param {
+        name: "passthrough",
+        forms: &[&[Slot::Arg("PREFIX", ExpectedArg::Prefix)]],
+        about: "Treat the command as a monitoring plugin and report its result.",
+    },
+    Param {
+        name: "output",
+        forms: &[&[Slot::Arg("FORMAT", ExpectedArg::Format)]],
+        about: "Choose the output format.",
+    },
+    Param {
+        name: "ascii",
+        forms: &[&[]],
+        about: "Mark states with plain text instead of emojis.",
+    },
+    Param {
+        name: "summary",
+        forms: &[&[Slot::Arg("TEMPLATE", ExpectedArg::Template)]],
+        about: "Print TEMPLATE filled in with the results as the first line.",
+    },
+    Param {
+        name: "verbose",
+        forms: &[&[]],
+        about: "Show alerts of all states, not just the worst ones.",
+    },
+    Param {
+        name: "show",
+        forms: &[&[
+            Slot::Arg("STREAM", ExpectedArg::Stream),
+            Slot::Arg("POLICY", ExpectedArg::Show),
+        ]],
+        about: "Choose when to show the command's STREAM.",
+    },
+    Param {
+        name: "showlimit",
+        forms: &[&[
+            Slot::Arg("UNIT", ExpectedArg::Limit),
+            Slot::Arg("HEAD", ExpectedArg::Count),
+            Slot::Arg("TAIL", ExpectedArg::Count),
+        ]],
+        about: "Show only the first HEAD and last TAIL lines/bytes of the command's output.",
+    },
+    Param {
+        name: "showmatching",
+        forms: &[&[Slot::Arg("CONTEXT", ExpectedArg::Count)]],
+        about: "Show only the output lines matching any pattern, with CONTEXT lines around them.",
+    },
+    Param {
+        name: "redact",
+        forms: &[&[MATCHER, PATTERN]],
+        about: "Replace PATTERN with ******** in all output.",
+    },
+    Param {
+        name: "time",
+        forms: PERFDATA,
+        about: "Check and report the command's run time in seconds.",
+    },
+    Param {
+        name: "exit",
+        forms: PERFDATA,
+        about: "Check and report the command's exit code.",
+    },
+    Param {
+        name: "signal",
+        forms: PERFDATA,
+        about: "Check and report the signal which terminated the command.",
+    },
+    Param {
+        name: "leftovers",
+        forms: PERFDATA,
+        about: "Check and report the command's processes left running.",
+    },
+    Param {
+        name: "cputime",
+        forms: PERFDATA,
+        about: "Check and report the command's CPU time in seconds.",
+    },
+    Param {
+        name: "maxrss",
+        forms: PERFDATA,
+        about: "Check and report the command's maximum resident set size in bytes.",
+    },
+    Param {
+        name: "majflt",
+        forms: PERFDATA,
+        about: "Check and report the command's major page faults.",
+    },
+    Param {
+        name: "ctxsw",
+        forms: PERFDATA,
+        about: "Check and report the command's context switches.",
+    },
+    Param {
+        name: "stdout",
+        forms: STREAM,
+        about: "Check and report what the command prints to stdout.",
+    },
+    Param {
+        name: "stderr",
+        forms: STREAM,
+        about: "Check and report what the command prints to stderr.",
+    },
+    Param {
+        name: "validate",
+        forms: &[&[]],
+        about: "Explain the other parameters instead of running the command.",
+    },
+    Param {
+        name: "help",
+        forms: &[&[], &[Slot::Arg("PARAM", ExpectedArg::Command)]],
+        about: "Describe all parameters or just PARAM.",
+    },
+    Param {
+        name: "version",
+        forms: &[&[]],
+        about: "Print the version.",
+    },
+];
+
+/// The words an argument may be, if it's a choice between some.
+pub(crate) fn choices(ea: &ExpectedArg) -> Option<&'static [&'static str]> {
+    match ea {
+        ExpectedArg::Matcher => Some(&[
+            "literal",
+            "regex",
+            "lines",
+            "inverted",
+            "bytes",
+            "extract",
+            "aggregate",
+            "json",
+        ]),
+        ExpectedArg::PatternKind => Some(&["literal", "regex"]),
+        ExpectedArg::State => Some(&["ok", "warning", "critical", "unknown"]),
+        ExpectedArg::Input => Some(&["literal", "file", "null"]),
+        ExpectedArg::Stream => Some(&["stdout", "stderr"]),
+        ExpectedArg::Aggregate => Some(&["sum", "min", "max", "avg", "last", "each"]),
+        ExpectedArg::Format => Some(&["text", "json"]),
+        ExpectedArg::Show => Some(&["never", "problem", "always"]),
+        ExpectedArg::Limit => Some(&["lines", "bytes"]),
+        _ => None,
+    }
+}
+
+/// Whether a form ends with WARN CRIT LABEL, which may also be given as named options.
+pub(crate) fn has_perfdata(form: &[Slot]) -> bool {
+    form.iter()
+        .any(|slot| matches!(slot, Slot::Arg(_, ExpectedArg::Label)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arg_parser::parse_cmdline;
+    use crate::cli::Request;
+    use std::ffi::OsString;
+
+    /// A valid value for every kind of argument which isn't a choice.
+    fn sample(ea: &ExpectedArg, config: &str) -> String {
+        match ea {
+            ExpectedArg::Pattern => "x",
+            ExpectedArg::Warning | ExpectedArg::Critical => "1",
+            ExpectedArg::Label => "label",
+            ExpectedArg::Command => "stdout",
+            ExpectedArg::WorkDir => "/",
+            ExpectedArg::Exe => "exe",
+            ExpectedArg::Seconds | ExpectedArg::Bytes | ExpectedArg::Count => "1",
+            ExpectedArg::EnvName => "NAME",
+            ExpectedArg::EnvValue | ExpectedArg::InputData => "value",
+            ExpectedArg::InputFile => "/dev/null",
+            ExpectedArg::Unit => "B",
+            ExpectedArg::JsonPath => "$.a",
+            ExpectedArg::Prefix => "p_",
+            ExpectedArg::Template => "{state}",
+            ExpectedArg::ConfigFile => config,
+            _ => return choices(ea).unwrap()[0].to_owned(),
+        }
+        .to_owned()
+    }
+
+    /// The words a slot may be, trying all choices.
+    fn alternatives(slot: &Slot, config: &str) -> Vec<String> {
+        match slot {
+            Slot::Words(words) => words.iter().map(|word| (*word).to_owned()).collect(),
+            Slot::Choice(ea) => choices(ea)
+                .unwrap()
+                .iter()
+                .map(|word| (*word).to_owned())
+                .collect(),
+            Slot::Arg(_, ea) | Slot::Optional(_, ea) => match choices(ea) {
+                None => vec![sample(ea, config)],
+                Some(words) => words.iter().map(|word| (*word).to_owned()).collect(),
+            },
+            Slot::Rest => vec!["arg".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_parser_accepts_all_forms() {
+        let config =
+            std::env::temp_dir().join(format!("check_rungrep_grammar_{}.toml", std::process::id()));
+        std::fs::write(&config, "cd = \"/\"\n").unwrap();
+        let config = config.into_os_string().into_string().unwrap();
+
+        for param in PARAMS {
+            for form in param.forms {
+                for (i, slot) in form.iter().enumerate() {
+                    for alternative in alternatives(slot, &config) {
+                        let mut args = vec!["prog".to_owned(), param.name.to_owned()];
+
+                        for (j, slot) in form.iter().enumerate() {
+                            args.push(match i == j {
+                                true => alternative.clone(),
+                                false => alternatives(slot, &config).swap_remove(0),
+                            });
+                        }
+
+                        args.extend(["command".to_owned(), "exe".to_owned()]);
+
+                        let (_, result) = parse_cmdline(args.iter().map(OsString::from));
+
+                        assert!(result.is_ok(), "rejected: {:?}", args);
+                    }
+                }
+
+                if form.iter().any(|slot| matches!(slot, Slot::Optional(..))) {
+                    let mut args = vec!["prog".to_owned(), param.name.to_owned()];
+
+                    for slot in form.iter() {
+                        if !matches!(slot, Slot::Optional(..)) {
+                            args.push(alternatives(slot, &config).swap_remove(0));
+                        }
+                    }
+
+                    args.extend(["command".to_owned(), "exe".to_owned()]);
+
+                    let (_, result) = parse_cmdline(args.iter().map(OsString::from));
+
+                    assert!(result.is_ok(), "rejected: {:?}", args);
+                }
+
+                if form.is_empty() {
+                    let (_, result) = parse_cmdline(
+                        ["prog", param.name, "command", "exe"]
+                            .into_iter()
+                            .map(OsString::from),
+                    );
+
+                    assert!(result.is_ok(), "rejected: {}", param.name);
+                }
+            }
+        }
+
+        std::fs::remove_file(config).unwrap();
+    }
+
+    #[test]
+    fn test_kinds_are_choices() {
+        for (name, ea) in [
+            ("stdout", ExpectedArg::Matcher),
+            ("stdin", ExpectedArg::Input),
+        ] {
+            let param = PARAMS.iter().find(|param| param.name == name).unwrap();
+            let kinds: Vec<_> = param
+                .forms
+                .iter()
+                .flat_map(|form| match &form[0] {
+                    Slot::Words(words) => *words,
+                    Slot::Choice(ea) => choices(ea).unwrap(),
+                    _ => &[],
+                })
+                .copied()
+                .collect();
+
+            assert_eq!(kinds, choices(&ea).unwrap(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parser_rejects_unknown_params() {
+        for name in ["stdin", "help", "foo"] {
+            let (_, result) = parse_cmdline(["prog", "help", name].into_iter().map(OsString::from));
+
+            match (name, result) {
+                ("foo", Err(_)) => {}
+                (_, Ok(Request::Help(Some(param)))) => assert_eq!(param, name),
+                _ => panic!("unexpected result for {}", name),
+            }
+        }
+    }
+}
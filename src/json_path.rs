@@ -0,0 +1,196 @@
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// A JSONPath-like selector, e.g. `$.items[0].size` or `$.items[*].name`.
+#[derive(Clone)]
+pub(crate) struct JsonPath {
+    source: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// What a path refers to in a document.
+pub(crate) enum Selection<'a> {
+    /// The path contains no wildcards and refers to this value, if any.
+    Single(Option<&'a Value>),
+    /// The path contains wildcards and refers to this many values.
+    Many(usize),
+}
+
+impl JsonPath {
+    pub(crate) fn parse(source: &str) -> Option<Self> {
+        let mut steps = Vec::new();
+        let mut rest = source.strip_prefix('$').unwrap_or(source);
+
+        while !rest.is_empty() {
+            match rest.strip_prefix('.') {
+                Some(dotted) => {
+                    let end = dotted.find(['.', '[']).unwrap_or(dotted.len());
+
+                    steps.push(match &dotted[..end] {
+                        "" => return None,
+                        "*" => Step::Wildcard,
+                        key => Step::Key(key.to_owned()),
+                    });
+
+                    rest = &dotted[end..];
+                }
+                None => {
+                    let (inner, after) = rest.strip_prefix('[')?.split_once(']')?;
+
+                    steps.push(match inner {
+                        "*" => Step::Wildcard,
+                        _ => match inner
+                            .strip_prefix('"')
+                            .and_then(|key| key.strip_suffix('"'))
+                            .or_else(|| inner.strip_prefix('\'')?.strip_suffix('\''))
+                        {
+                            Some(key) => Step::Key(key.to_owned()),
+                            None => Step::Index(inner.parse().ok()?),
+                        },
+                    });
+
+                    rest = after;
+                }
+            }
+        }
+
+        Some(Self {
+            source: source.to_owned(),
+            steps,
+        })
+    }
+
+    pub(crate) fn select<'a>(&self, document: &'a Value) -> Selection<'a> {
+        let mut current = vec![document];
+
+        for step in &self.steps {
+            current = current
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value> {
+                    match (step, value) {
+                        (Step::Key(key), Value::Object(object)) => {
+                            object.get(key).into_iter().collect()
+                        }
+                        (Step::Index(index), Value::Array(array)) => {
+                            array.get(*index).into_iter().collect()
+                        }
+                        (Step::Wildcard, Value::Array(array)) => array.iter().collect(),
+                        (Step::Wildcard, Value::Object(object)) => object.values().collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+
+        if self.steps.contains(&Step::Wildcard) {
+            Selection::Many(current.len())
+        } else {
+            Selection::Single(current.pop())
+        }
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Turns a value into a number, if it has an obvious numeric meaning:
+/// numbers as they are, booleans as 0/1, arrays and objects as their length
+/// and strings if they contain a number.
+pub(crate) fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Null => None,
+        Value::Bool(bool) => Some(*bool as u8 as f64),
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        Value::Array(array) => Some(array.len() as f64),
+        Value::Object(object) => Some(object.len() as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn steps(path: &str) -> Option<Vec<Step>> {
+        JsonPath::parse(path).map(|path| path.steps)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(steps("$"), Some(vec![]));
+        assert_eq!(
+            steps("$.items[0].size"),
+            Some(vec![
+                Step::Key("items".to_owned()),
+                Step::Index(0),
+                Step::Key("size".to_owned())
+            ])
+        );
+        assert_eq!(
+            steps("$[*].*['a.b'][\"c\"]"),
+            Some(vec![
+                Step::Wildcard,
+                Step::Wildcard,
+                Step::Key("a.b".to_owned()),
+                Step::Key("c".to_owned())
+            ])
+        );
+        assert_eq!(steps(".a"), Some(vec![Step::Key("a".to_owned())]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(steps("$.").is_none());
+        assert!(steps("$..a").is_none());
+        assert!(steps("$[x]").is_none());
+        assert!(steps("$[0").is_none());
+        assert!(steps("$a").is_none());
+    }
+
+    #[test]
+    fn test_select_single() {
+        let doc = json!({"items": [{"size": 3}, {"size": 5}]});
+
+        match JsonPath::parse("$.items[1].size").unwrap().select(&doc) {
+            Selection::Single(Some(value)) => assert_eq!(value, &json!(5)),
+            _ => panic!("expected a single value"),
+        }
+
+        match JsonPath::parse("$.items[2].size").unwrap().select(&doc) {
+            Selection::Single(None) => {}
+            _ => panic!("expected nothing"),
+        }
+    }
+
+    #[test]
+    fn test_select_many() {
+        let doc = json!({"items": [{"size": 3}, {"size": 5}, {"name": "x"}]});
+
+        match JsonPath::parse("$.items[*].size").unwrap().select(&doc) {
+            Selection::Many(count) => assert_eq!(count, 2),
+            _ => panic!("expected a count"),
+        }
+    }
+
+    #[test]
+    fn test_numeric() {
+        assert_eq!(numeric(&json!(1.5)), Some(1.5));
+        assert_eq!(numeric(&json!(true)), Some(1.0));
+        assert_eq!(numeric(&json!("42")), Some(42.0));
+        assert_eq!(numeric(&json!([1, 2, 3])), Some(3.0));
+        assert_eq!(numeric(&json!({})), Some(0.0));
+        assert_eq!(numeric(&json!("running")), None);
+        assert_eq!(numeric(&json!(null)), None);
+    }
+}
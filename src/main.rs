@@ -2,45 +2,245 @@ mod alerts;
 mod arg_parser;
 mod cli;
 mod cnt_iter;
+mod config;
+mod dump;
+mod explain;
+mod grammar;
+mod json_path;
+mod passthrough;
 mod plugin;
 mod process;
+mod redact;
+mod scan;
+mod template;
 mod usage;
 
-use crate::cli::{Condition, Matcher};
+use crate::cli::{
+    Aggregate, Condition, Extraction, Format, Input, Matcher, Request, Resource, Show, Stream,
+};
+use crate::dump::excerpt;
+use crate::json_path::{JsonPath, Selection};
 use crate::plugin::Check;
-use alerts::{ExecTime, ExitCode, Matches};
-use memchr::memmem::find_iter;
+use alerts::{
+    BadJson, Bytes, ExecTime, ExitCode, Extracted, JsonProblem, JsonValue, Killed, Leftovers,
+    Matches, MatchingLines, NoUsage, NothingExtracted, Passthrough, ResourceUsage, Stopped,
+    TimedOut,
+};
 use plugin::{Perfdat, Perfdata, State};
+use process::{Captured, Job};
+use redact::Redactor;
+use scan::{Extractor, Scanner};
+use serde_json::{json, Value};
 use std::env::{args_os, set_current_dir, var_os};
 use std::io;
 use std::io::Write;
+use std::iter::once;
 use std::process::exit;
 
 fn main() {
-    let (_, rtodo, consumed) = arg_parser::parse_cmdline(args_os());
+    plugin::set_ascii(var_os("CHECK_RUNGREP_ASCII").is_some_and(|ascii| !ascii.is_empty()));
+
+    let cli: Vec<_> = args_os().collect();
+    let (_, rtodo) = arg_parser::parse_cmdline(cli.iter().cloned());
     match rtodo {
-        Err(err) => {
-            usage::blame_user(err, consumed);
+        Err(rejected) => {
+            if rejected.partial.ascii {
+                plugin::set_ascii(true);
+            }
+
+            usage::blame_user(
+                rejected.errors,
+                cli.get(1..).unwrap_or_default(),
+                &Redactor {
+                    secrets: rejected.partial.redact,
+                },
+            );
             exit(3);
         }
-        Ok(todo) => {
+        Ok(Request::Validate(todo)) => {
+            let redactor = Redactor {
+                secrets: todo.redact.clone(),
+            };
+
+            print!("{}", redactor.text(&explain::explain(&todo)));
+            exit(0);
+        }
+        Ok(Request::Help(param)) => {
+            print!("{}", usage::help(param.as_deref()));
+            exit(0);
+        }
+        Ok(Request::Version) => {
+            println!("check_rungrep {}", env!("CARGO_PKG_VERSION"));
+            exit(0);
+        }
+        Ok(Request::Run(todo)) => {
+            let todo = *todo;
+
+            if todo.ascii {
+                plugin::set_ascii(true);
+            }
+
+            plugin::set_json(matches!(todo.format, Format::Json));
+
             for cd in todo.cd {
-                match set_current_dir(cd.as_os_str()) {
-                    Err(err) => {
-                        println!("☯️ chdir(2): {}", err);
-                        exit(3);
+                if let Err(err) = set_current_dir(cd.as_os_str()) {
+                    plugin::give_up(format_args!("chdir(2): {}", err));
+                }
+            }
+
+            let mut stdout_scanners = Vec::new();
+            let mut stderr_scanners = Vec::new();
+            let mut stdout_extractors = Vec::new();
+            let mut stderr_extractors = Vec::new();
+            // What "showmatching" looks for
+            let mut stdout_patterns = Vec::new();
+            let mut stderr_patterns = Vec::new();
+
+            for condition in &todo.conditions {
+                match condition {
+                    Condition::Stdout(matcher, _) => {
+                        stdout_scanners.push(Scanner::new(matcher.clone()));
+                        stdout_patterns.push(matcher.clone());
+                    }
+                    Condition::Stderr(matcher, _) => {
+                        stderr_scanners.push(Scanner::new(matcher.clone()));
+                        stderr_patterns.push(matcher.clone());
                     }
-                    Ok(_) => {}
+                    Condition::Lines(stream, lines, matcher, _) => {
+                        match stream {
+                            Stream::Stdout => &mut stdout_scanners,
+                            Stream::Stderr => &mut stderr_scanners,
+                        }
+                        .push(Scanner::lines(matcher.clone(), *lines));
+
+                        match stream {
+                            Stream::Stdout => &mut stdout_patterns,
+                            Stream::Stderr => &mut stderr_patterns,
+                        }
+                        .push(matcher.clone());
+                    }
+                    Condition::Extract(stream, extraction, _) => {
+                        match stream {
+                            Stream::Stdout => &mut stdout_extractors,
+                            Stream::Stderr => &mut stderr_extractors,
+                        }
+                        .push(Extractor::new(extraction.clone()));
+
+                        match stream {
+                            Stream::Stdout => &mut stdout_patterns,
+                            Stream::Stderr => &mut stderr_patterns,
+                        }
+                        .push(Matcher::RegExp(extraction.regex.clone()));
+                    }
+                    _ => {}
                 }
             }
 
-            let (stdout, stderr, code, time) = process::run_cmd(
-                todo.exe,
-                todo.args,
-                var_os("CHECK_RUNGREP_STDIN").filter(|s| !s.is_empty()),
-            );
+            for stop in &todo.stop_on {
+                match stop.stream {
+                    Stream::Stdout => &mut stdout_patterns,
+                    Stream::Stderr => &mut stderr_patterns,
+                }
+                .push(stop.matcher.clone());
+            }
+
+            let command: Vec<_> = once(&todo.exe)
+                .chain(&todo.args)
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
 
+            let output = process::run_cmd(Job {
+                exe: todo.exe,
+                args: todo.args,
+                env: todo.env,
+                input: todo.stdin.or_else(|| {
+                    var_os("CHECK_RUNGREP_STDIN")
+                        .filter(|s| !s.is_empty())
+                        .map(Input::Literal)
+                }),
+                timeout: todo.timeout.as_ref().map(|timeout| timeout.after),
+                sweep: todo
+                    .conditions
+                    .iter()
+                    .any(|condition| matches!(condition, Condition::Leftovers(_))),
+                max_output: todo.max_output,
+                stdout_scanners,
+                stderr_scanners,
+                stdout_extractors,
+                stderr_extractors,
+                stop_on: todo.stop_on.clone(),
+            });
+
+            if output.signal.is_some()
+                && !output.timed_out
+                && output.stopped.is_none()
+                && !todo
+                    .conditions
+                    .iter()
+                    .any(|condition| matches!(condition, Condition::Signal(_)))
+            {
+                plugin::give_up("waitpid(2): child was killed");
+            }
+
+            let (mut stdout, mut stderr, time) = (output.stdout, output.stderr, output.time);
+            let mut stdout_scanners = std::mem::take(&mut stdout.scanners).into_iter();
+            let mut stderr_scanners = std::mem::take(&mut stderr.scanners).into_iter();
+            let mut stdout_extractors = std::mem::take(&mut stdout.extractors).into_iter();
+            let mut stderr_extractors = std::mem::take(&mut stderr.extractors).into_iter();
             let mut check = Check::new();
+
+            if todo.verbose {
+                check.show_all();
+            }
+
+            match output.stopped {
+                None => {}
+                Some(stopped) => {
+                    let stop = &todo.stop_on[stopped.index];
+
+                    check.alert(
+                        Box::new(Stopped {
+                            source: stop.stream.name(),
+                            matcher: stop.matcher.clone(),
+                            after: stopped.after,
+                        }),
+                        State::Ok,
+                    );
+                }
+            }
+
+            match todo.timeout {
+                Some(timeout) if output.timed_out => {
+                    check.alert(
+                        Box::new(TimedOut {
+                            after: timeout.after,
+                            held_open: output.held_open,
+                        }),
+                        timeout.state,
+                    );
+                }
+                _ => {}
+            }
+
+            match &todo.passthrough {
+                None => {}
+                Some(prefix) => {
+                    let wrapped = passthrough::parse(&stdout.data, prefix);
+
+                    check.alert(
+                        Box::new(Passthrough {
+                            code: output.code,
+                            text: wrapped.text,
+                        }),
+                        passthrough::state(output.code),
+                    );
+
+                    for perfdata in wrapped.perfdata {
+                        check.perfdata(perfdata);
+                    }
+                }
+            }
+
             for condition in todo.conditions {
                 match condition {
                     Condition::ExecSeconds(thresholds) => {
@@ -51,7 +251,7 @@ fn main() {
                             }),
                             Perfdata {
                                 value: time.as_secs_f64(),
-                                uom: "s",
+                                uom: "s".into(),
                                 thresholds,
                                 min: Some(0.0),
                                 max: None,
@@ -59,6 +259,12 @@ fn main() {
                         );
                     }
                     Condition::ExitCode(thresholds) => {
+                        // A terminated command didn't return anything
+                        let code = match output.code {
+                            None => continue,
+                            Some(code) => code,
+                        };
+
                         check.add(
                             Box::new(ExitCode {
                                 code,
@@ -66,27 +272,246 @@ fn main() {
                             }),
                             Perfdata {
                                 value: code as f64,
-                                uom: "",
+                                uom: "".into(),
                                 thresholds,
                                 min: None,
                                 max: None,
                             },
                         );
                     }
+                    Condition::Signal(thresholds) => {
+                        let number = match &output.signal {
+                            None => 0,
+                            Some(signal) => signal.number,
+                        };
+
+                        check.add(
+                            Box::new(Killed {
+                                signal: output.signal.clone(),
+                                thresholds: thresholds.thresholds.clone(),
+                            }),
+                            Perfdata {
+                                value: number as f64,
+                                uom: "".into(),
+                                thresholds,
+                                min: Some(0.0),
+                                max: None,
+                            },
+                        );
+                    }
+                    Condition::Leftovers(thresholds) => {
+                        check.add(
+                            Box::new(Leftovers {
+                                processes: output.leftovers,
+                                thresholds: thresholds.thresholds.clone(),
+                            }),
+                            Perfdata {
+                                value: output.leftovers as f64,
+                                uom: "".into(),
+                                thresholds,
+                                min: Some(0.0),
+                                max: None,
+                            },
+                        );
+                    }
+                    Condition::Usage(resource, thresholds) => match output.usage {
+                        None => check.alert(Box::new(NoUsage), State::Unknown),
+                        Some(usage) => {
+                            let (value, uom) = match resource {
+                                Resource::CpuTime => {
+                                    ((usage.user + usage.system).as_secs_f64(), "s")
+                                }
+                                Resource::MaxRss => (usage.max_rss as f64, "B"),
+                                Resource::MajorFaults => (usage.major_faults as f64, "c"),
+                                Resource::ContextSwitches => (usage.context_switches as f64, "c"),
+                            };
+
+                            check.add(
+                                Box::new(ResourceUsage {
+                                    resource,
+                                    usage,
+                                    thresholds: thresholds.thresholds.clone(),
+                                }),
+                                Perfdata {
+                                    value,
+                                    uom: uom.into(),
+                                    thresholds,
+                                    min: Some(0.0),
+                                    max: None,
+                                },
+                            );
+                        }
+                    },
                     Condition::Stdout(matcher, thresholds) => {
-                        handle_matcher(&mut check, "stdout", &stdout, matcher, thresholds);
+                        handle_matcher(
+                            &mut check,
+                            "stdout",
+                            stdout_scanners.next(),
+                            matcher,
+                            thresholds,
+                        );
                     }
                     Condition::Stderr(matcher, thresholds) => {
-                        handle_matcher(&mut check, "stderr", &stderr, matcher, thresholds);
+                        handle_matcher(
+                            &mut check,
+                            "stderr",
+                            stderr_scanners.next(),
+                            matcher,
+                            thresholds,
+                        );
+                    }
+                    Condition::Lines(stream, lines, matcher, thresholds) => {
+                        let scanners = match stream {
+                            Stream::Stdout => &mut stdout_scanners,
+                            Stream::Stderr => &mut stderr_scanners,
+                        };
+
+                        let times = match scanners.next() {
+                            None => panic!("no scanner for {} lines condition", stream.name()),
+                            Some(scanner) => scanner.times(),
+                        };
+
+                        check.add(
+                            Box::new(MatchingLines {
+                                source: stream.name(),
+                                lines,
+                                matcher,
+                                times,
+                                thresholds: thresholds.thresholds.clone(),
+                            }),
+                            Perfdata {
+                                value: times as f64,
+                                uom: "".into(),
+                                thresholds,
+                                min: Some(0.0),
+                                max: None,
+                            },
+                        );
+                    }
+                    Condition::Extract(stream, extraction, thresholds) => {
+                        handle_extractor(
+                            &mut check,
+                            stream.name(),
+                            match stream {
+                                Stream::Stdout => stdout_extractors.next(),
+                                Stream::Stderr => stderr_extractors.next(),
+                            },
+                            extraction,
+                            thresholds,
+                        );
+                    }
+                    Condition::Json(stream, path, thresholds) => {
+                        handle_json(
+                            &mut check,
+                            stream.name(),
+                            match stream {
+                                Stream::Stdout => &stdout,
+                                Stream::Stderr => &stderr,
+                            },
+                            path,
+                            thresholds,
+                        );
+                    }
+                    Condition::Bytes(stream, thresholds) => {
+                        let captured = match stream {
+                            Stream::Stdout => &stdout,
+                            Stream::Stderr => &stderr,
+                        };
+
+                        check.add(
+                            Box::new(Bytes {
+                                source: stream.name(),
+                                total: captured.total,
+                                kept: captured.data.len(),
+                                thresholds: thresholds.thresholds.clone(),
+                            }),
+                            Perfdata {
+                                value: captured.total as f64,
+                                uom: "B".into(),
+                                thresholds,
+                                min: Some(0.0),
+                                max: None,
+                            },
+                        );
                     }
                 }
             }
 
-            print!("{}", check);
+            match &todo.summary {
+                None => {}
+                Some(template) => {
+                    // The built-ins win, perfdata labelled alike is still there with a field
+                    let summary = template.render(|name, field| match (name, field) {
+                        ("state", None) => Some(check.state().name().to_uppercase()),
+                        ("exit", None) => Some(match output.code {
+                            None => "-".to_owned(),
+                            Some(code) => code.to_string(),
+                        }),
+                        ("time", None) => Some(format!("{:.3}s", time.as_secs_f64())),
+                        (label, field) => check.perfdata_field(label, field),
+                    });
 
-            if check.state() != State::Ok {
-                show_out("STDOUT", stdout);
-                show_out("STDERR", stderr);
+                    check.summarize(summary);
+                }
+            }
+
+            let dump = &todo.dump;
+            let redactor = Redactor {
+                secrets: todo.redact,
+            };
+            let shown = |show: Show, patterns: &[Matcher], captured: &Captured| {
+                let show = match show {
+                    Show::Never => false,
+                    Show::Problem => check.state() != State::Ok,
+                    Show::Always => true,
+                };
+
+                show.then(|| {
+                    excerpt(
+                        &redactor.bytes(&captured.data),
+                        dump.context,
+                        patterns,
+                        dump.limit,
+                    )
+                })
+            };
+
+            let stdout_shown = shown(dump.stdout, &stdout_patterns, &stdout);
+            let stderr_shown = shown(dump.stderr, &stderr_patterns, &stderr);
+
+            match todo.format {
+                Format::Text => {
+                    print!("{}", redactor.text(&check.to_string()));
+
+                    match stdout_shown {
+                        None => {}
+                        Some(shown) => show_out("STDOUT", &stdout, &shown),
+                    }
+
+                    match stderr_shown {
+                        None => {}
+                        Some(shown) => show_out("STDERR", &stderr, &shown),
+                    }
+                }
+                Format::Json => {
+                    let mut json = check.json();
+
+                    json["command"] = command.into();
+                    json["seconds"] = time.as_secs_f64().into();
+
+                    match stdout_shown {
+                        None => {}
+                        Some(shown) => json["stdout"] = captured_json(&stdout, &shown),
+                    }
+
+                    match stderr_shown {
+                        None => {}
+                        Some(shown) => json["stderr"] = captured_json(&stderr, &shown),
+                    }
+
+                    redactor.json(&mut json);
+                    println!("{}", json);
+                }
             }
 
             exit(check.state() as i32);
@@ -97,13 +522,13 @@ fn main() {
 fn handle_matcher(
     check: &mut Check,
     source: &'static str,
-    data: &Vec<u8>,
+    scanner: Option<Scanner>,
     matcher: Matcher,
     thresholds: Perfdat,
 ) {
-    let times = match &matcher {
-        Matcher::Literal(literal) => find_iter(data.as_slice(), literal).count(),
-        Matcher::RegExp(regexp) => regexp.find_iter(data.as_slice()).count(),
+    let times = match scanner {
+        None => panic!("no scanner for {} condition", source),
+        Some(scanner) => scanner.times(),
     };
 
     check.add(
@@ -115,7 +540,7 @@ fn handle_matcher(
         }),
         Perfdata {
             value: times as f64,
-            uom: "",
+            uom: "".into(),
             thresholds,
             min: Some(0.0),
             max: None,
@@ -123,16 +548,149 @@ fn handle_matcher(
     );
 }
 
-fn show_out(h1: &str, data: Vec<u8>) {
+fn handle_extractor(
+    check: &mut Check,
+    source: &'static str,
+    extractor: Option<Extractor>,
+    extraction: Extraction,
+    thresholds: Perfdat,
+) {
+    let extractor = match extractor {
+        None => panic!("no extractor for {} extract condition", source),
+        Some(extractor) => extractor,
+    };
+
+    let values = match extraction.aggregate {
+        Aggregate::Each => extractor.each().to_vec(),
+        _ => match extractor.value() {
+            None => Vec::new(),
+            Some(value) => vec![(String::new(), value)],
+        },
+    };
+
+    if values.is_empty() {
+        check.alert(
+            Box::new(NothingExtracted {
+                source,
+                regex: extraction.regex,
+            }),
+            State::Unknown,
+        );
+        return;
+    }
+
+    for (key, value) in values {
+        // One perfdata entry per label, e.g. disk_sda, disk_sdb
+        let label = if key.is_empty() || thresholds.label.is_empty() {
+            thresholds.label.clone()
+        } else {
+            format!("{}_{}", thresholds.label, key)
+        };
+
+        check.add(
+            Box::new(Extracted {
+                source,
+                regex: extraction.regex.clone(),
+                aggregate: extraction.aggregate,
+                count: extractor.count(),
+                key,
+                value,
+                uom: extraction.uom.clone(),
+                thresholds: thresholds.thresholds.clone(),
+            }),
+            Perfdata {
+                value,
+                uom: extraction.uom.clone().into(),
+                thresholds: Perfdat {
+                    thresholds: thresholds.thresholds.clone(),
+                    label,
+                },
+                min: None,
+                max: None,
+            },
+        );
+    }
+}
+
+fn handle_json(
+    check: &mut Check,
+    source: &'static str,
+    captured: &Captured,
+    path: JsonPath,
+    thresholds: Perfdat,
+) {
+    match select_json(captured, &path) {
+        Err(problem) => check.alert(
+            Box::new(BadJson {
+                source,
+                path,
+                problem,
+            }),
+            State::Unknown,
+        ),
+        Ok((value, counted)) => check.add(
+            Box::new(JsonValue {
+                source,
+                path,
+                value,
+                counted,
+                thresholds: thresholds.thresholds.clone(),
+            }),
+            Perfdata {
+                value,
+                uom: "".into(),
+                thresholds,
+                min: None,
+                max: None,
+            },
+        ),
+    }
+}
+
+/// Returns the number at the path and whether it's a count of elements.
+fn select_json(captured: &Captured, path: &JsonPath) -> Result<(f64, bool), JsonProblem> {
+    if captured.truncated() {
+        return Err(JsonProblem::Truncated);
+    }
+
+    let document = serde_json::from_slice(&captured.data).map_err(JsonProblem::Malformed)?;
+
+    match path.select(&document) {
+        Selection::Many(count) => Ok((count as f64, true)),
+        Selection::Single(None) => Err(JsonProblem::Missing),
+        Selection::Single(Some(value)) => match json_path::numeric(value) {
+            None => Err(JsonProblem::NotNumeric),
+            Some(number) => Ok((number, false)),
+        },
+    }
+}
+
+fn captured_json(captured: &Captured, shown: &[u8]) -> Value {
+    json!({
+        "data": String::from_utf8_lossy(shown),
+        "total": captured.total,
+        "truncated": captured.truncated(),
+    })
+}
+
+fn show_out(h1: &str, captured: &Captured, shown: &[u8]) {
     println!();
     println!("{}", h1);
     println!("======");
     println!();
 
-    match io::stdout().write(data.as_ref()) {
+    match io::stdout().write_all(shown) {
         Ok(_) => {}
         Err(err) => {
             panic!("failed printing to stdout: {err}");
         }
     }
+
+    if captured.truncated() {
+        println!();
+        println!(
+            "[{} more bytes not captured]",
+            captured.total - captured.data.len() as u64
+        );
+    }
 }
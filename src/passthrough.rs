@@ -0,0 +1,172 @@
+use crate::plugin::{Perfdat, Perfdata, State, Thresholds};
+use nagios_range::NagiosRange;
+use std::borrow::Cow;
+
+/// What a wrapped monitoring plugin reported.
+pub(crate) struct PluginOutput {
+    pub(crate) text: String,
+    pub(crate) perfdata: Vec<Perfdata>,
+}
+
+/// Interprets a wrapped monitoring plugin's exit code.
+pub(crate) fn state(code: Option<i32>) -> State {
+    match code {
+        Some(0) => State::Ok,
+        Some(1) => State::Warning,
+        Some(2) => State::Critical,
+        _ => State::Unknown,
+    }
+}
+
+/// Parses a wrapped monitoring plugin's `TEXT | PERFDATA` output,
+/// with optional long text lines and perfdata after another `|` in them.
+/// Perfdata labels get prefix prepended, malformed perfdata is ignored.
+pub(crate) fn parse(data: &[u8], prefix: &str) -> PluginOutput {
+    let output = String::from_utf8_lossy(data);
+    let (first, rest) = output.split_once('\n').unwrap_or((&output, ""));
+    let (text, mut perfdata) = match first.split_once('|') {
+        None => (first, String::new()),
+        Some((text, perfdata)) => (text, perfdata.to_owned()),
+    };
+
+    match rest.split_once('|') {
+        None => {}
+        Some((_, more)) => {
+            perfdata.push(' ');
+            perfdata.push_str(more);
+        }
+    }
+
+    PluginOutput {
+        text: text.trim().to_owned(),
+        perfdata: split(&perfdata)
+            .iter()
+            .filter_map(|entry| parse_perfdatum(entry, prefix))
+            .collect(),
+    }
+}
+
+/// Splits perfdata by whitespace outside of quoted labels.
+fn split(perfdata: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut entry = String::new();
+    let mut quoted = false;
+
+    for c in perfdata.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                entry.push(c);
+            }
+            _ if c.is_whitespace() && !quoted => {
+                if !entry.is_empty() {
+                    entries.push(std::mem::take(&mut entry));
+                }
+            }
+            _ => entry.push(c),
+        }
+    }
+
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Parses `'LABEL'=VALUE[UOM];[WARN];[CRIT];[MIN];[MAX]`.
+fn parse_perfdatum(entry: &str, prefix: &str) -> Option<Perfdata> {
+    let (label, values) = entry.rsplit_once('=')?;
+    let label = match label.strip_prefix('\'') {
+        None => label.to_owned(),
+        Some(quoted) => quoted.strip_suffix('\'')?.replace("''", "'"),
+    };
+
+    if label.is_empty() {
+        return None;
+    }
+
+    let mut values = values.split(';');
+    let value = values.next()?;
+    let number_end = value
+        .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+        .unwrap_or(value.len());
+    let mut next = || values.next().filter(|field| !field.is_empty());
+
+    Some(Perfdata {
+        value: value[..number_end].parse().ok()?,
+        uom: Cow::Owned(value[number_end..].to_owned()),
+        thresholds: Perfdat {
+            thresholds: Thresholds {
+                warn: next().and_then(|warn| NagiosRange::from(warn).ok()),
+                crit: next().and_then(|crit| NagiosRange::from(crit).ok()),
+            },
+            label: format!("{}{}", prefix, label),
+        },
+        min: next().and_then(|min| min.parse().ok()),
+        max: next().and_then(|max| max.parse().ok()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state() {
+        assert!(state(Some(0)) == State::Ok);
+        assert!(state(Some(2)) == State::Critical);
+        assert!(state(Some(3)) == State::Unknown);
+        assert!(state(Some(127)) == State::Unknown);
+        assert!(state(None) == State::Unknown);
+    }
+
+    #[test]
+    fn test_parse_text_only() {
+        let output = parse(b"DISK OK - free space: / 3326 MB\n", "");
+        assert_eq!(output.text, "DISK OK - free space: / 3326 MB");
+        assert!(output.perfdata.is_empty());
+    }
+
+    #[test]
+    fn test_parse_perfdata() {
+        let output = parse(
+            b"LOAD OK | load1=0.5;5;10;0; 'load 5'=0.25;; 'it''s'=3s\nlong text\nmore | tmp=10MB;;;0;100\n",
+            "wrapped_",
+        );
+        assert_eq!(output.text, "LOAD OK");
+
+        let labels: Vec<_> = output
+            .perfdata
+            .iter()
+            .map(|perfdata| perfdata.thresholds.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "wrapped_load1",
+                "wrapped_load 5",
+                "wrapped_it's",
+                "wrapped_tmp"
+            ]
+        );
+
+        let load1 = &output.perfdata[0];
+        assert_eq!(load1.value, 0.5);
+        assert!(load1.thresholds.thresholds.warn.is_some());
+        assert!(load1.thresholds.thresholds.crit.is_some());
+        assert_eq!(load1.min, Some(0.0));
+        assert_eq!(load1.max, None);
+
+        assert_eq!(output.perfdata[2].uom, "s");
+        assert_eq!(output.perfdata[3].uom, "MB");
+        assert_eq!(output.perfdata[3].max, Some(100.0));
+    }
+
+    #[test]
+    fn test_parse_malformed_perfdata() {
+        let output = parse(b"OK | =1 a=b c d=1 'e=2", "");
+        assert_eq!(output.perfdata.len(), 1);
+        assert_eq!(output.perfdata[0].thresholds.label, "d");
+    }
+}
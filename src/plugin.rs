@@ -1,24 +1,75 @@
 use nagios_range::NagiosRange;
+use serde_json::{json, Value};
+use std::borrow::Cow;
 use std::cmp::max;
 use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether to mark states with plain ASCII instead of emojis.
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+/// Whether to report as JSON instead of text, also when giving up early.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// Reports that the command couldn't be run or watched and exits as UNKNOWN.
+pub(crate) fn give_up(message: impl Display) -> ! {
+    match JSON.load(Ordering::Relaxed) {
+        false => println!("{} {}", State::Unknown.marker(), message),
+        true => println!(
+            "{}",
+            json!({
+                "state": State::Unknown.name(),
+                "code": State::Unknown as i32,
+                "message": message.to_string(),
+            })
+        ),
+    }
+
+    exit(3);
+}
 
 pub(crate) struct Check {
     state: State,
+    summary: Option<String>,
+    verbose: bool,
     perfdata: Vec<Perfdata>,
-    alerts: BTreeMap<State, Vec<Box<dyn Display>>>,
+    /// How many matches or numbers the perfdata of a label stems from, if known.
+    counts: BTreeMap<String, u64>,
+    alerts: BTreeMap<State, Vec<Box<dyn Alert>>>,
 }
 
-#[derive(Ord, Eq, PartialOrd, PartialEq, Clone)]
+/// Something worth reporting about the command.
+pub(crate) trait Alert: Display {
+    /// Machine-readable details, as a JSON object.
+    fn details(&self) -> Value {
+        json!({})
+    }
+}
+
+impl Alert for &str {}
+
+/// Ordered by severity (CRITICAL outranks UNKNOWN), not by exit code.
+#[derive(Eq, PartialEq, Clone)]
 pub(crate) enum State {
     Ok = 0,
     Warning = 1,
     Critical = 2,
+    Unknown = 3,
 }
 
 pub(crate) struct Perfdata {
     pub(crate) value: f64,
-    pub(crate) uom: &'static str,
+    pub(crate) uom: Cow<'static, str>,
     pub(crate) thresholds: Perfdat,
     pub(crate) min: Option<f64>,
     pub(crate) max: Option<f64>,
@@ -39,12 +90,15 @@ impl Check {
     pub(crate) fn new() -> Self {
         Self {
             state: State::Ok,
+            summary: None,
+            verbose: false,
             perfdata: Vec::new(),
+            counts: BTreeMap::new(),
             alerts: BTreeMap::new(),
         }
     }
 
-    pub(crate) fn add(&mut self, alert: Box<dyn Display>, perfdata: Perfdata) {
+    pub(crate) fn add(&mut self, alert: Box<dyn Alert>, perfdata: Perfdata) {
         let my_state = if threshold_alert(perfdata.value, &perfdata.thresholds.thresholds.crit) {
             State::Critical
         } else if threshold_alert(perfdata.value, &perfdata.thresholds.thresholds.warn) {
@@ -53,17 +107,144 @@ impl Check {
             State::Ok
         };
 
-        self.state = max(self.state.clone(), my_state.clone());
-        self.alerts.entry(my_state).or_default().push(alert);
-
         if !perfdata.thresholds.label.is_empty() {
+            if let Some(count) = alert.details().get("count").and_then(Value::as_u64) {
+                self.counts.insert(perfdata.thresholds.label.clone(), count);
+            }
+
             self.perfdata.push(perfdata);
         }
+
+        self.alert(alert, my_state);
+    }
+
+    /// Adds perfdata as it is, without checking its thresholds.
+    pub(crate) fn perfdata(&mut self, perfdata: Perfdata) {
+        self.perfdata.push(perfdata);
+    }
+
+    pub(crate) fn alert(&mut self, alert: Box<dyn Alert>, state: State) {
+        self.state = max(self.state.clone(), state.clone());
+        self.alerts.entry(state).or_default().push(alert);
     }
 
     pub(crate) fn state(&self) -> State {
         self.state.clone()
     }
+
+    /// Sets a line to show before all alerts.
+    pub(crate) fn summarize(&mut self, summary: String) {
+        self.summary = Some(summary);
+    }
+
+    /// Makes the text output show the alerts of all states, not only the worst one.
+    pub(crate) fn show_all(&mut self) {
+        self.verbose = true;
+    }
+
+    /// Returns the value and unit of the perfdata with the given label, if any,
+    /// or just its "value", "uom", "count" (of matches or numbers), "warn" or "crit".
+    pub(crate) fn perfdata_field(&self, label: &str, field: Option<&str>) -> Option<String> {
+        let perfdat = self
+            .perfdata
+            .iter()
+            .find(|perfdat| perfdat.thresholds.label == label)?;
+        let thresholds = &perfdat.thresholds.thresholds;
+
+        match field {
+            None => Some(format!("{}{}", perfdat.value, perfdat.uom)),
+            Some("value") => Some(perfdat.value.to_string()),
+            Some("uom") => Some(perfdat.uom.to_string()),
+            Some("count") => self.counts.get(label).map(u64::to_string),
+            Some("warn") => thresholds.warn.map(|warn| warn.to_string()),
+            Some("crit") => thresholds.crit.map(|crit| crit.to_string()),
+            Some(_) => None,
+        }
+    }
+
+    /// Returns all alerts (not only the ones of the final state) and perfdata.
+    pub(crate) fn json(&self) -> Value {
+        let mut alerts = Vec::new();
+
+        for (state, by_state) in &self.alerts {
+            for alert in by_state {
+                let mut details = alert.details();
+
+                if let Value::Object(details) = &mut details {
+                    details.insert("state".to_owned(), state.name().into());
+                    details.insert("message".to_owned(), alert.to_string().into());
+                }
+
+                alerts.push(details);
+            }
+        }
+
+        json!({
+            "state": self.state.name(),
+            "summary": self.summary,
+            "code": self.state.clone() as i32,
+            "alerts": alerts,
+            "perfdata": self.perfdata.iter().map(|perfdat| json!({
+                "label": perfdat.thresholds.label,
+                "value": perfdat.value,
+                "uom": perfdat.uom,
+                "warning": perfdat.thresholds.thresholds.warn.map(|warn| warn.to_string()),
+                "critical": perfdat.thresholds.thresholds.crit.map(|crit| crit.to_string()),
+                "min": perfdat.min,
+                "max": perfdat.max,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.severity().cmp(&other.severity())
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl State {
+    fn severity(&self) -> u8 {
+        match self {
+            State::Ok => 0,
+            State::Warning => 1,
+            State::Unknown => 2,
+            State::Critical => 3,
+        }
+    }
+
+    /// Returns what to prefix messages of this state with.
+    pub(crate) fn marker(&self) -> &'static str {
+        self.marker_for(ASCII.load(Ordering::Relaxed))
+    }
+
+    fn marker_for(&self, ascii: bool) -> &'static str {
+        match (self, ascii) {
+            (State::Ok, false) => "✅",
+            (State::Warning, false) => "⚠️",
+            (State::Critical, false) => "🚨",
+            (State::Unknown, false) => "☯️",
+            (State::Ok, true) => "[OK]",
+            (State::Warning, true) => "[WARNING]",
+            (State::Critical, true) => "[CRITICAL]",
+            (State::Unknown, true) => "[UNKNOWN]",
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            State::Ok => "ok",
+            State::Warning => "warning",
+            State::Critical => "critical",
+            State::Unknown => "unknown",
+        }
+    }
 }
 
 fn threshold_alert(value: f64, threshold: &Option<NagiosRange>) -> bool {
@@ -75,14 +256,15 @@ fn threshold_alert(value: f64, threshold: &Option<NagiosRange>) -> bool {
 
 impl Display for Check {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self.alerts.get(&self.state) {
+        match &self.summary {
             None => {}
-            Some(alerts) => {
-                let li = match self.state {
-                    State::Ok => "✅",
-                    State::Warning => "⚠️",
-                    State::Critical => "🚨",
-                };
+            Some(summary) => writeln!(f, "{} {}", self.state.marker(), summary)?,
+        }
+
+        // Worst first
+        for (state, alerts) in self.alerts.iter().rev() {
+            if self.verbose || *state == self.state {
+                let li = state.marker();
 
                 for alert in alerts {
                     writeln!(f, "{} {}", li, alert)?;
@@ -118,6 +300,25 @@ impl Display for Check {
     }
 }
 
+struct OptionDisplay<T>
+where
+    T: Display,
+{
+    o: Option<T>,
+}
+
+impl<T> Display for OptionDisplay<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.o {
+            None => Ok(()),
+            Some(v) => v.fmt(f),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,11 +354,97 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_check_perfdata_ignores_thresholds() {
+        let mut check = Check::new();
+        check.perfdata(Perfdata {
+            value: 5.0,
+            uom: "".into(),
+            thresholds: crit_threshold("3"),
+            min: None,
+            max: None,
+        });
+        assert!(check.state() == State::Ok);
+        assert_eq!(check.perfdata.len(), 1);
+    }
+
+    #[test]
+    fn test_check_json() {
+        let mut check = Check::new();
+        check.add(
+            Box::new("fine"),
+            Perfdata {
+                value: 1.0,
+                uom: "s".into(),
+                thresholds: Perfdat {
+                    label: "time".to_owned(),
+                    ..crit_threshold("5")
+                },
+                min: Some(0.0),
+                max: None,
+            },
+        );
+        check.alert(Box::new("off"), State::Warning);
+
+        let json = check.json();
+        assert_eq!(json["state"], "warning");
+        assert_eq!(json["code"], 1);
+        assert_eq!(json["alerts"][0]["state"], "ok");
+        assert_eq!(json["alerts"][0]["message"], "fine");
+        assert_eq!(json["alerts"][1]["message"], "off");
+        assert_eq!(json["perfdata"][0]["label"], "time");
+        assert_eq!(json["perfdata"][0]["value"], 1.0);
+        assert_eq!(json["perfdata"][0]["warning"], Value::Null);
+        assert!(json["perfdata"][0]["critical"].is_string());
+        assert_eq!(json["perfdata"][0]["min"], 0.0);
+    }
+
+    #[test]
+    fn test_check_summary_comes_first() {
+        let mut check = Check::new();
+        check.alert(Box::new("long explanation"), State::Warning);
+        check.summarize("short".to_owned());
+        assert_eq!(check.to_string(), "⚠️ short\n⚠️ long explanation\n");
+        assert_eq!(check.json()["summary"], "short");
+    }
+
+    #[test]
+    fn test_check_display_only_worst_state() {
+        let mut check = Check::new();
+        check.alert(Box::new("fine"), State::Ok);
+        check.alert(Box::new("bad"), State::Critical);
+        check.alert(Box::new("odd"), State::Warning);
+        assert_eq!(check.to_string(), "🚨 bad\n");
+    }
+
+    #[test]
+    fn test_check_display_all_states_worst_first() {
+        let mut check = Check::new();
+        check.alert(Box::new("fine"), State::Ok);
+        check.alert(Box::new("bad"), State::Critical);
+        check.alert(Box::new("odd"), State::Warning);
+        check.alert(Box::new("worse"), State::Critical);
+        check.show_all();
+        assert_eq!(check.to_string(), "🚨 bad\n🚨 worse\n⚠️ odd\n✅ fine\n");
+    }
+
+    #[test]
+    fn test_ascii_markers() {
+        // Not via set_ascii(), as other tests running in parallel expect emojis
+        assert_eq!(State::Ok.marker_for(true), "[OK]");
+        assert_eq!(State::Warning.marker_for(true), "[WARNING]");
+        assert_eq!(State::Critical.marker_for(true), "[CRITICAL]");
+        assert_eq!(State::Unknown.marker_for(true), "[UNKNOWN]");
+        assert_eq!(State::Unknown.marker_for(false), "☯️");
+    }
+
     #[test]
     fn test_state_ordering() {
         assert!(State::Ok < State::Warning);
         assert!(State::Warning < State::Critical);
         assert!(State::Ok < State::Critical);
+        assert!(State::Warning < State::Unknown);
+        assert!(State::Unknown < State::Critical);
     }
 
     #[test]
@@ -173,7 +460,7 @@ mod tests {
             Box::new("alert"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: no_thresholds(),
                 min: None,
                 max: None,
@@ -190,7 +477,7 @@ mod tests {
             Box::new("alert"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: warn_threshold("@0:10"),
                 min: None,
                 max: None,
@@ -206,7 +493,7 @@ mod tests {
             Box::new("alert"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: crit_threshold("@0:10"),
                 min: None,
                 max: None,
@@ -222,7 +509,7 @@ mod tests {
             Box::new("warn alert"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: warn_threshold("@0:10"),
                 min: None,
                 max: None,
@@ -234,7 +521,7 @@ mod tests {
             Box::new("crit alert"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: crit_threshold("@0:10"),
                 min: None,
                 max: None,
@@ -250,7 +537,7 @@ mod tests {
             Box::new("everything fine"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: no_thresholds(),
                 min: None,
                 max: None,
@@ -268,7 +555,7 @@ mod tests {
             Box::new("something off"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: warn_threshold("@0:10"),
                 min: None,
                 max: None,
@@ -286,7 +573,7 @@ mod tests {
             Box::new("bad things"),
             Perfdata {
                 value: 5.0,
-                uom: "",
+                uom: "".into(),
                 thresholds: crit_threshold("@0:10"),
                 min: None,
                 max: None,
@@ -297,6 +584,29 @@ mod tests {
         assert!(s.contains("bad things"));
     }
 
+    #[test]
+    fn test_check_critical_outranks_unknown() {
+        let mut check = Check::new();
+        check.alert(Box::new("bad"), State::Critical);
+        check.alert(Box::new("odd"), State::Unknown);
+        check.alert(Box::new("fine"), State::Ok);
+        assert!(check.state() == State::Critical);
+        assert_eq!(check.state() as i32, 2);
+        assert_eq!(check.to_string(), "🚨 bad\n");
+        check.show_all();
+        assert_eq!(check.to_string(), "🚨 bad\n☯️ odd\n✅ fine\n");
+    }
+
+    #[test]
+    fn test_check_alert_with_fixed_state() {
+        let mut check = Check::new();
+        check.alert(Box::new("timed out"), State::Unknown);
+        assert!(check.state() == State::Unknown);
+        let s = check.to_string();
+        assert!(s.contains("☯️"));
+        assert!(s.contains("timed out"));
+    }
+
     #[test]
     fn test_check_display_perfdata_included_when_label_nonempty() {
         let mut check = Check::new();
@@ -304,7 +614,7 @@ mod tests {
             Box::new("alert"),
             Perfdata {
                 value: 42.0,
-                uom: "s",
+                uom: "s".into(),
                 thresholds: Perfdat {
                     thresholds: Thresholds {
                         warn: None,
@@ -329,7 +639,7 @@ mod tests {
             Box::new("alert"),
             Perfdata {
                 value: 42.0,
-                uom: "s",
+                uom: "s".into(),
                 thresholds: no_thresholds(),
                 min: None,
                 max: None,
@@ -339,22 +649,3 @@ mod tests {
         assert!(!s.contains('|'));
     }
 }
-
-struct OptionDisplay<T>
-where
-    T: Display,
-{
-    o: Option<T>,
-}
-
-impl<T> Display for OptionDisplay<T>
-where
-    T: Display,
-{
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match &self.o {
-            None => Ok(()),
-            Some(v) => v.fmt(f),
-        }
-    }
-}
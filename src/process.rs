@@ -1,34 +1,164 @@
+use crate::cli::{EnvOp, Input, StopOn, Stream};
+use crate::plugin::give_up;
+use crate::scan::{Extractor, Scanner};
 use std::ffi::OsString;
-use std::io::Write;
-use std::process::{exit, Command, Stdio};
-use std::thread::spawn;
+use std::fs::File;
+#[cfg(unix)]
+use std::fs::{read_dir, read_to_string};
+use std::io::{ErrorKind, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{sleep, spawn, JoinHandle};
 use std::time::{Duration, Instant};
 
-pub(crate) fn run_cmd(
-    exe: OsString,
-    args: Vec<OsString>,
-    input: Option<OsString>,
-) -> (Vec<u8>, Vec<u8>, i32, Duration) {
+/// How long a terminated child may take to exit before it gets killed.
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// How often to check whether the child is done while a deadline is pending.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(crate) struct Output {
+    pub(crate) stdout: Captured,
+    pub(crate) stderr: Captured,
+    pub(crate) code: Option<i32>,
+    pub(crate) signal: Option<Signal>,
+    pub(crate) time: Duration,
+    pub(crate) timed_out: bool,
+    /// The command exited, but processes it left behind kept its output open.
+    pub(crate) held_open: bool,
+    pub(crate) stopped: Option<Stopped>,
+    pub(crate) leftovers: usize,
+    pub(crate) usage: Option<Usage>,
+}
+
+/// Everything a command wrote to one of its output streams, up to a limit,
+/// and the scanners and extractors which have seen all of it.
+#[derive(Default)]
+pub(crate) struct Captured {
+    pub(crate) data: Vec<u8>,
+    pub(crate) total: u64,
+    pub(crate) scanners: Vec<Scanner>,
+    pub(crate) extractors: Vec<Extractor>,
+}
+
+impl Captured {
+    pub(crate) fn truncated(&self) -> bool {
+        self.total > self.data.len() as u64
+    }
+}
+
+/// Which stop pattern made us terminate the command and when.
+#[derive(Clone, Copy)]
+pub(crate) struct Stopped {
+    pub(crate) index: usize,
+    pub(crate) after: Duration,
+}
+
+#[derive(Clone)]
+pub(crate) struct Signal {
+    pub(crate) number: i32,
+    pub(crate) description: String,
+    pub(crate) core_dumped: bool,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Usage {
+    pub(crate) user: Duration,
+    pub(crate) system: Duration,
+    pub(crate) max_rss: u64,
+    pub(crate) major_faults: u64,
+    pub(crate) context_switches: u64,
+}
+
+/// What to run and how.
+pub(crate) struct Job {
+    pub(crate) exe: OsString,
+    pub(crate) args: Vec<OsString>,
+    pub(crate) env: Vec<EnvOp>,
+    pub(crate) input: Option<Input>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) sweep: bool,
+    pub(crate) max_output: Option<usize>,
+    pub(crate) stdout_scanners: Vec<Scanner>,
+    pub(crate) stderr_scanners: Vec<Scanner>,
+    pub(crate) stdout_extractors: Vec<Extractor>,
+    pub(crate) stderr_extractors: Vec<Extractor>,
+    pub(crate) stop_on: Vec<StopOn>,
+}
+
+pub(crate) fn run_cmd(job: Job) -> Output {
+    let Job {
+        exe,
+        args,
+        env,
+        input,
+        timeout,
+        sweep,
+        max_output,
+        stdout_scanners,
+        stderr_scanners,
+        stdout_extractors,
+        stderr_extractors,
+        stop_on,
+    } = job;
+
     let mut cmd = Command::new(exe);
 
+    for op in env {
+        match op {
+            EnvOp::Set(name, value) => {
+                cmd.env(name, value);
+            }
+            EnvOp::Unset(name) => {
+                cmd.env_remove(name);
+            }
+            EnvOp::Clear => {
+                cmd.env_clear();
+            }
+        }
+    }
+
+    let (stdin, data) = match input {
+        None => (Stdio::piped(), None),
+        Some(Input::Literal(data)) => (Stdio::piped(), Some(data)),
+        Some(Input::File(path)) => match File::open(path) {
+            Err(err) => {
+                give_up(format_args!("open(2): {}", err));
+            }
+            Ok(file) => (Stdio::from(file), None),
+        },
+        Some(Input::Null) => (Stdio::null(), None),
+    };
+
     cmd.args(args)
-        .stdin(Stdio::piped())
+        .stdin(stdin)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
+    // Own process group, so that helpers forked by the command can be found later.
+    // Otherwise stay in ours, so that whoever kills us on a timeout of their own
+    // (e.g. the monitoring system) also kills the command.
+    let grouped = timeout.is_some() || sweep || !stop_on.is_empty();
+
+    #[cfg(unix)]
+    if grouped {
+        cmd.process_group(0);
+    }
+
     let start = Instant::now();
 
     match cmd.spawn() {
         Err(err) => {
-            println!("☯️ exec(3): {}", err);
-            exit(3);
+            give_up(format_args!("exec(3): {}", err));
         }
         Ok(mut child) => {
             match child.stdin.take() {
                 None => {}
                 Some(mut stdin) => {
                     spawn(move || {
-                        match input {
+                        match data {
                             None => {}
                             Some(data) => {
                                 drop(stdin.write_all(data.into_encoded_bytes().as_slice()));
@@ -40,22 +170,431 @@ pub(crate) fn run_cmd(
                 }
             }
 
-            match child.wait_with_output() {
-                Err(err) => {
-                    println!("☯️ waitpid(2): {}", err);
-                    exit(3);
+            let stoppable = !stop_on.is_empty();
+            let stopped = Arc::new(OnceLock::new());
+            let mut stdout_stoppers = Vec::new();
+            let mut stderr_stoppers = Vec::new();
+
+            for (index, stop) in stop_on.into_iter().enumerate() {
+                match stop.stream {
+                    Stream::Stdout => &mut stdout_stoppers,
+                    Stream::Stderr => &mut stderr_stoppers,
                 }
-                Ok(result) => {
-                    let end = Instant::now();
-                    match result.status.code() {
-                        None => {
-                            println!("☯️ waitpid(2): child was killed");
-                            exit(3);
+                .push(Stopper {
+                    index,
+                    scanner: Scanner::new(stop.matcher),
+                });
+            }
+
+            let mut stdout = Reader::new(
+                child.stdout.take(),
+                max_output,
+                stdout_scanners,
+                stdout_extractors,
+                Watch {
+                    start,
+                    stoppers: stdout_stoppers,
+                    stopped: stopped.clone(),
+                },
+            );
+
+            let mut stderr = Reader::new(
+                child.stderr.take(),
+                max_output,
+                stderr_scanners,
+                stderr_extractors,
+                Watch {
+                    start,
+                    stoppers: stderr_stoppers,
+                    stopped: stopped.clone(),
+                },
+            );
+
+            let mut timed_out = false;
+
+            let status = if timeout.is_none() && !stoppable {
+                wait(&mut child)
+            } else {
+                loop {
+                    match child.try_wait() {
+                        Err(err) => {
+                            give_up(format_args!("waitpid(2): {}", err));
                         }
-                        Some(code) => (result.stdout, result.stderr, code, end - start),
+                        Ok(Some(status)) => break status,
+                        Ok(None) => {}
+                    }
+
+                    if stopped.get().is_some() {
+                        break terminate(&mut child);
+                    }
+
+                    if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                        timed_out = true;
+                        break terminate(&mut child);
                     }
+
+                    sleep(POLL_INTERVAL);
+                }
+            };
+
+            let end = Instant::now();
+            let usage = usage();
+            let was_stopped = stopped.get().is_some();
+
+            let mut leftovers = if grouped && (sweep || timed_out || was_stopped) {
+                sweep_group(&child)
+            } else {
+                0
+            };
+
+            // The pipes may still be held open by the child's own children.
+            let eof_deadline = if timed_out || was_stopped {
+                Some(end + KILL_GRACE)
+            } else {
+                timeout.map(|timeout| start + timeout)
+            };
+
+            let mut held_open = false;
+
+            // Only possible with a deadline, i.e. in an own process group
+            if ![&mut stdout, &mut stderr]
+                .into_iter()
+                .all(|reader| reader.wait(eof_deadline))
+            {
+                // Terminate them, so that they don't outlive us
+                let swept = sweep_group(&child);
+
+                if !timed_out && !was_stopped {
+                    timed_out = true;
+                    held_open = true;
+                    leftovers += swept;
+                }
+
+                let eof_deadline = Some(Instant::now() + KILL_GRACE);
+
+                for reader in [&mut stdout, &mut stderr] {
+                    reader.wait(eof_deadline);
                 }
             }
+
+            Output {
+                stdout: stdout.take(),
+                stderr: stderr.take(),
+                code: status.code(),
+                signal: signal(&status),
+                time: end - start,
+                timed_out,
+                held_open,
+                stopped: stopped.get().copied(),
+                leftovers,
+                usage,
+            }
+        }
+    }
+}
+
+fn wait(child: &mut Child) -> ExitStatus {
+    match child.wait() {
+        Err(err) => {
+            give_up(format_args!("waitpid(2): {}", err));
         }
+        Ok(status) => status,
+    }
+}
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<Signal> {
+    use std::ffi::CStr;
+    use std::os::unix::process::ExitStatusExt;
+
+    status.signal().map(|number| Signal {
+        number,
+        description: {
+            let description = unsafe { libc::strsignal(number) };
+
+            if description.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(description) }
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        },
+        core_dumped: status.core_dumped(),
+    })
+}
+
+#[cfg(not(unix))]
+fn signal(_: &ExitStatus) -> Option<Signal> {
+    None
+}
+
+/// Resources used by all waited-for children, i.e. the command.
+#[cfg(unix)]
+fn usage() -> Option<Usage> {
+    let mut ru = std::mem::MaybeUninit::<libc::rusage>::uninit();
+
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, ru.as_mut_ptr()) } != 0 {
+        return None;
+    }
+
+    let ru = unsafe { ru.assume_init() };
+
+    // Linux and most BSDs report KiB, macOS reports bytes
+    let rss_unit = if cfg!(any(target_os = "macos", target_os = "ios")) {
+        1
+    } else {
+        1024
+    };
+
+    Some(Usage {
+        user: timeval(ru.ru_utime),
+        system: timeval(ru.ru_stime),
+        max_rss: (ru.ru_maxrss as u64).saturating_mul(rss_unit),
+        major_faults: ru.ru_majflt as u64,
+        context_switches: (ru.ru_nvcsw as u64).saturating_add(ru.ru_nivcsw as u64),
+    })
+}
+
+#[cfg(not(unix))]
+fn usage() -> Option<Usage> {
+    None
+}
+
+#[cfg(unix)]
+fn timeval(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+}
+
+/// Asks the child (and its process group) to terminate
+/// and kills it if it doesn't within `KILL_GRACE`.
+fn terminate(child: &mut Child) -> ExitStatus {
+    #[cfg(unix)]
+    {
+        signal_group(child, libc::SIGTERM);
+
+        let deadline = Instant::now() + KILL_GRACE;
+
+        while Instant::now() < deadline {
+            match child.try_wait() {
+                Ok(Some(status)) => return status,
+                Ok(None) => sleep(POLL_INTERVAL),
+                Err(_) => break,
+            }
+        }
+
+        signal_group(child, libc::SIGKILL);
+    }
+
+    drop(child.kill());
+    wait(child)
+}
+
+/// Terminates whatever remained of the (already exited) child's process group
+/// and returns how many processes that were.
+#[cfg(unix)]
+fn sweep_group(child: &Child) -> usize {
+    let pgid = child.id() as libc::pid_t;
+    let leftovers = count_group(pgid);
+
+    if leftovers > 0 {
+        signal_group(child, libc::SIGTERM);
+
+        let deadline = Instant::now() + KILL_GRACE;
+
+        while count_group(pgid) > 0 {
+            if Instant::now() >= deadline {
+                signal_group(child, libc::SIGKILL);
+                break;
+            }
+
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    leftovers
+}
+
+#[cfg(not(unix))]
+fn sweep_group(_: &Child) -> usize {
+    0
+}
+
+/// Sends the signal to the child's process group and tells whether anyone received it.
+#[cfg(unix)]
+fn signal_group(child: &Child, signal: libc::c_int) -> bool {
+    unsafe { libc::kill(-(child.id() as libc::pid_t), signal) == 0 }
+}
+
+/// Counts the living processes in the given group via procfs if available.
+/// Otherwise only tells whether there are any.
+#[cfg(unix)]
+fn count_group(pgid: libc::pid_t) -> usize {
+    match read_dir("/proc") {
+        Err(_) => {
+            if unsafe { libc::kill(-pgid, 0) } == 0 {
+                1
+            } else {
+                0
+            }
+        }
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                match read_to_string(entry.path().join("stat")) {
+                    Err(_) => false,
+                    Ok(stat) => {
+                        // pid (comm) state ppid pgrp ...
+                        let mut fields = match stat.rfind(')') {
+                            None => return false,
+                            Some(comm_end) => stat[comm_end + 1..].split_whitespace(),
+                        };
+
+                        match (fields.next(), fields.nth(1)) {
+                            (Some(state), Some(pgrp)) => state != "Z" && pgrp.parse() == Ok(pgid),
+                            _ => false,
+                        }
+                    }
+                }
+            })
+            .count(),
+    }
+}
+
+/// A stop pattern being looked for in one of the output streams.
+struct Stopper {
+    index: usize,
+    scanner: Scanner,
+}
+
+/// Stop patterns of one output stream and where to report the first match.
+struct Watch {
+    start: Instant,
+    stoppers: Vec<Stopper>,
+    stopped: Arc<OnceLock<Stopped>>,
+}
+
+impl Watch {
+    fn feed(&mut self, chunk: &[u8]) {
+        for stopper in &mut self.stoppers {
+            stopper.scanner.feed(chunk);
+
+            if stopper.scanner.found() {
+                self.stopped.get_or_init(|| Stopped {
+                    index: stopper.index,
+                    after: self.start.elapsed(),
+                });
+            }
+        }
+    }
+}
+
+/// Collects everything a child writes into a pipe, in the background.
+/// Beyond the limit (if any) the pipe is still drained, but only counted and scanned.
+struct Reader {
+    captured: Arc<Mutex<Captured>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Reader {
+    fn new<R>(
+        pipe: Option<R>,
+        limit: Option<usize>,
+        scanners: Vec<Scanner>,
+        extractors: Vec<Extractor>,
+        mut watch: Watch,
+    ) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        let captured = Arc::new(Mutex::new(Captured {
+            scanners,
+            extractors,
+            ..Captured::default()
+        }));
+
+        let thread = pipe.map(|mut pipe| {
+            let captured = captured.clone();
+
+            spawn(move || {
+                let mut chunk = [0u8; 8192];
+
+                loop {
+                    match pipe.read(&mut chunk) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            let mut captured = lock(&captured);
+                            let keep = match limit {
+                                None => n,
+                                Some(limit) => n.min(limit.saturating_sub(captured.data.len())),
+                            };
+
+                            captured.data.extend_from_slice(&chunk[..keep]);
+                            captured.total += n as u64;
+
+                            for scanner in &mut captured.scanners {
+                                scanner.feed(&chunk[..n]);
+                            }
+
+                            for extractor in &mut captured.extractors {
+                                extractor.feed(&chunk[..n]);
+                            }
+
+                            watch.feed(&chunk[..n]);
+                        }
+                        Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                        Err(_) => break,
+                    }
+                }
+            })
+        });
+
+        Self { captured, thread }
+    }
+
+    /// Waits for EOF until the deadline (if any) and tells whether EOF was reached.
+    fn wait(&mut self, deadline: Option<Instant>) -> bool {
+        match self.thread.take() {
+            None => true,
+            Some(thread) => {
+                match deadline {
+                    None => {}
+                    Some(deadline) => {
+                        while !thread.is_finished() {
+                            if Instant::now() >= deadline {
+                                self.thread = Some(thread);
+                                return false;
+                            }
+
+                            sleep(POLL_INTERVAL);
+                        }
+                    }
+                }
+
+                drop(thread.join());
+                true
+            }
+        }
+    }
+
+    fn take(&self) -> Captured {
+        let mut captured = std::mem::take(&mut *lock(&self.captured));
+
+        for scanner in &mut captured.scanners {
+            scanner.finish();
+        }
+
+        for extractor in &mut captured.extractors {
+            extractor.finish();
+        }
+
+        captured
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(err) => err.into_inner(),
     }
 }
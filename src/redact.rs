@@ -0,0 +1,118 @@
+use crate::cli::Matcher;
+use memchr::memmem::find_iter;
+use regex::bytes::NoExpand;
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// What secrets are replaced with.
+pub(crate) const MASK: &[u8] = b"********";
+
+/// Masks secrets in everything shown to the user.
+pub(crate) struct Redactor {
+    pub(crate) secrets: Vec<Matcher>,
+}
+
+impl Redactor {
+    pub(crate) fn bytes<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut data = Cow::Borrowed(data);
+
+        for secret in &self.secrets {
+            let redacted = match secret {
+                Matcher::Literal(literal) => {
+                    let mut redacted = Vec::new();
+                    let mut end = 0;
+
+                    for start in find_iter(&data, literal) {
+                        redacted.extend_from_slice(&data[end..start]);
+                        redacted.extend_from_slice(MASK);
+                        end = start + literal.len();
+                    }
+
+                    match end {
+                        0 => None,
+                        _ => {
+                            redacted.extend_from_slice(&data[end..]);
+                            Some(redacted)
+                        }
+                    }
+                }
+                Matcher::RegExp(regexp) => match regexp.replace_all(&data, NoExpand(MASK)) {
+                    Cow::Borrowed(_) => None,
+                    Cow::Owned(redacted) => Some(redacted),
+                },
+            };
+
+            match redacted {
+                None => {}
+                Some(redacted) => data = Cow::Owned(redacted),
+            }
+        }
+
+        data
+    }
+
+    pub(crate) fn text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.bytes(text.as_bytes()) {
+            Cow::Borrowed(_) => Cow::Borrowed(text),
+            Cow::Owned(redacted) => Cow::Owned(String::from_utf8_lossy(&redacted).into_owned()),
+        }
+    }
+
+    /// Masks secrets in all strings inside a JSON value.
+    pub(crate) fn json(&self, value: &mut Value) {
+        match value {
+            Value::String(string) => match self.text(string) {
+                Cow::Borrowed(_) => {}
+                Cow::Owned(redacted) => *string = redacted,
+            },
+            Value::Array(array) => {
+                for item in array {
+                    self.json(item);
+                }
+            }
+            Value::Object(object) => {
+                for (_, item) in object.iter_mut() {
+                    self.json(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::bytes::Regex;
+    use serde_json::json;
+
+    fn redactor() -> Redactor {
+        Redactor {
+            secrets: vec![
+                Matcher::Literal(b"hunter2".to_vec()),
+                Matcher::RegExp(Regex::new("token=[0-9a-f]+").unwrap()),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(
+            redactor()
+                .bytes(b"pw hunter2, token=c0ffee, pw hunter2 $1")
+                .as_ref(),
+            b"pw ********, ********, pw ******** $1"
+        );
+        assert!(matches!(redactor().bytes(b"nothing"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut value = json!({"message": "pw hunter2", "list": ["token=1", 7], "n": 1});
+        redactor().json(&mut value);
+        assert_eq!(
+            value,
+            json!({"message": "pw ********", "list": ["********", 7], "n": 1})
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use crate::cli::{Aggregate, Extraction, Lines, Matcher};
+use memchr::memchr;
+use memchr::memmem::{find, find_iter};
+use regex::bytes::Regex;
+
+/// Lines longer than this are split, so that memory usage stays bounded.
+const MAX_LINE: usize = 1 << 20;
+
+/// Regular expression matches up to this long are counted exactly
+/// while data is searched in windows of twice this size.
+const MAX_MATCH: usize = 1 << 16;
+
+/// How much already searched data to keep for `\b`, `(?m)^` and the like.
+const LOOK_BEHIND: usize = 4;
+
+/// Counts the matches of a pattern (or the lines (not) matching it)
+/// in data arriving chunk by chunk.
+///
+/// Patterns are searched for in the whole data, across chunk boundaries,
+/// like `find_iter()` would do if all data was in memory.
+/// Regular expression matches longer than `MAX_MATCH` may be missed or count differently.
+/// Lines are counted one by one instead.
+pub(crate) struct Scanner {
+    matcher: Matcher,
+    lines: Option<Lines>,
+    buffer: LineBuffer,
+    /// Where in the pending data to continue searching for regular expressions.
+    at: usize,
+    /// Where the last match ended, so that no empty match is found there again.
+    last_end: Option<usize>,
+    times: usize,
+}
+
+impl Scanner {
+    pub(crate) fn new(matcher: Matcher) -> Self {
+        Self {
+            matcher,
+            lines: None,
+            buffer: LineBuffer::default(),
+            at: 0,
+            last_end: None,
+            times: 0,
+        }
+    }
+
+    pub(crate) fn lines(matcher: Matcher, lines: Lines) -> Self {
+        Self {
+            lines: Some(lines),
+            ..Self::new(matcher)
+        }
+    }
+
+    pub(crate) fn feed(&mut self, chunk: &[u8]) {
+        match (&self.matcher, self.lines) {
+            (Matcher::Literal(literal), None) => {
+                let pending = &mut self.buffer.pending;
+                let mut end = 0;
+
+                pending.extend_from_slice(chunk);
+
+                for start in find_iter(pending.as_slice(), literal) {
+                    self.times += 1;
+                    end = start + literal.len();
+                }
+
+                // Keep what may be the beginning of a match continued by the next chunk
+                pending.drain(..end.max((pending.len() + 1).saturating_sub(literal.len())));
+            }
+            (Matcher::RegExp(_), None) => {
+                self.buffer.pending.extend_from_slice(chunk);
+
+                if self.buffer.pending.len() >= MAX_MATCH * 2 {
+                    self.search(false);
+                }
+            }
+            (matcher, Some(lines)) => {
+                let times = &mut self.times;
+
+                self.buffer
+                    .feed(chunk, |line| *times += count(matcher, lines, line));
+            }
+        }
+    }
+
+    /// Processes what's left over after the last chunk.
+    pub(crate) fn finish(&mut self) {
+        match (&self.matcher, self.lines) {
+            (Matcher::Literal(_), None) => self.buffer.pending.clear(),
+            (Matcher::RegExp(_), None) => {
+                self.search(true);
+                self.buffer.pending.clear();
+            }
+            (matcher, Some(lines)) => {
+                let times = &mut self.times;
+
+                self.buffer
+                    .finish(|line| *times += count(matcher, lines, line));
+            }
+        }
+    }
+
+    /// Counts the regular expression matches in the pending data
+    /// which can't change anymore, whatever follows, and drops what's done.
+    fn search(&mut self, last: bool) {
+        let regexp = match &self.matcher {
+            Matcher::RegExp(regexp) => regexp,
+            Matcher::Literal(_) => return,
+        };
+
+        let pending = &mut self.buffer.pending;
+        let len = pending.len();
+
+        while let Some(found) = regexp.find_at(pending, self.at) {
+            if !last && found.start() + MAX_MATCH > len {
+                break;
+            }
+
+            // Like find_iter(), don't match the empty string right after a match
+            if found.is_empty() && self.last_end == Some(found.end()) {
+                if found.end() >= len {
+                    break;
+                }
+
+                self.at = found.end() + 1;
+                continue;
+            }
+
+            self.times += 1;
+            self.at = found.end();
+            self.last_end = Some(found.end());
+        }
+
+        // Nothing before can start a match shorter than MAX_MATCH anymore
+        self.at = self.at.max(len.saturating_sub(MAX_MATCH));
+
+        let done = self.at.saturating_sub(LOOK_BEHIND);
+
+        pending.drain(..done);
+        self.at -= done;
+        self.last_end = self.last_end.and_then(|end| end.checked_sub(done));
+    }
+
+    pub(crate) fn times(&self) -> usize {
+        self.times
+    }
+
+    /// Tells whether anything matched so far, as if there was no more data.
+    pub(crate) fn found(&self) -> bool {
+        self.times > 0
+            || match (&self.matcher, self.lines) {
+                (Matcher::RegExp(regexp), None) => {
+                    regexp.find_at(&self.buffer.pending, self.at).is_some()
+                }
+                _ => false,
+            }
+    }
+}
+
+fn count(matcher: &Matcher, lines: Lines, line: &[u8]) -> usize {
+    match lines {
+        Lines::Matching => matches(matcher, line) as usize,
+        Lines::NotMatching => !matches(matcher, line) as usize,
+    }
+}
+
+/// Tells whether a line contains a match.
+pub(crate) fn matches(matcher: &Matcher, line: &[u8]) -> bool {
+    match matcher {
+        Matcher::Literal(literal) => find(line, literal).is_some(),
+        Matcher::RegExp(regexp) => regexp.is_match(line),
+    }
+}
+
+/// Parses numbers captured by a regular expression from each line
+/// of data arriving chunk by chunk and aggregates them.
+///
+/// The number is taken from the capture group named "value" if any,
+/// otherwise from the first capture group not named "label" if any,
+/// otherwise from the whole match.
+/// In "each" mode, every number is kept along with the capture group named "label"
+/// (or its position if there's no such group).
+pub(crate) struct Extractor {
+    extraction: Extraction,
+    buffer: LineBuffer,
+    values: Values,
+}
+
+#[derive(Default)]
+struct Values {
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+    last: f64,
+    each: Vec<(String, f64)>,
+}
+
+impl Extractor {
+    pub(crate) fn new(extraction: Extraction) -> Self {
+        Self {
+            extraction,
+            buffer: LineBuffer::default(),
+            values: Values::default(),
+        }
+    }
+
+    pub(crate) fn feed(&mut self, chunk: &[u8]) {
+        let (extraction, values) = (&self.extraction, &mut self.values);
+
+        self.buffer
+            .feed(chunk, |line| extract(extraction, line, values));
+    }
+
+    /// Processes what's left over after the last chunk.
+    pub(crate) fn finish(&mut self) {
+        let (extraction, values) = (&self.extraction, &mut self.values);
+
+        self.buffer.finish(|line| extract(extraction, line, values));
+    }
+
+    /// How many numbers were found.
+    pub(crate) fn count(&self) -> usize {
+        self.values.count
+    }
+
+    /// The aggregate of all numbers found, if any.
+    pub(crate) fn value(&self) -> Option<f64> {
+        let values = &self.values;
+
+        match self.extraction.aggregate {
+            _ if values.count < 1 => None,
+            Aggregate::Sum => Some(values.sum),
+            Aggregate::Min => Some(values.min),
+            Aggregate::Max => Some(values.max),
+            Aggregate::Avg => Some(values.sum / values.count as f64),
+            Aggregate::Last | Aggregate::Each => Some(values.last),
+        }
+    }
+
+    /// Every number found along with its label, in "each" mode.
+    pub(crate) fn each(&self) -> &[(String, f64)] {
+        &self.values.each
+    }
+}
+
+fn extract(extraction: &Extraction, line: &[u8], values: &mut Values) {
+    let regex = &extraction.regex;
+
+    for captures in regex.captures_iter(line) {
+        let capture = match captures.name("value") {
+            Some(capture) => capture,
+            None => match value_group(regex).map(|group| captures.get(group)) {
+                Some(Some(capture)) => capture,
+                Some(None) => continue,
+                None => match captures.get(0) {
+                    None => continue,
+                    Some(capture) => capture,
+                },
+            },
+        };
+
+        let value: f64 = match std::str::from_utf8(capture.as_bytes()).map(|s| s.trim().parse()) {
+            Ok(Ok(value)) => value,
+            _ => continue,
+        };
+
+        if values.count < 1 {
+            values.min = value;
+            values.max = value;
+        }
+
+        values.count += 1;
+        values.sum += value;
+        values.min = values.min.min(value);
+        values.max = values.max.max(value);
+        values.last = value;
+
+        if extraction.aggregate == Aggregate::Each {
+            let label = match captures.name("label") {
+                None => values.count.to_string(),
+                Some(label) => String::from_utf8_lossy(label.as_bytes()).into_owned(),
+            };
+
+            // A label seen again gets its value updated, like in "last" mode
+            match values.each.iter_mut().find(|(seen, _)| *seen == label) {
+                None => values.each.push((label, value)),
+                Some(seen) => seen.1 = value,
+            }
+        }
+    }
+}
+
+/// Returns the first capture group other than the one named "label", if any.
+fn value_group(regex: &Regex) -> Option<usize> {
+    regex
+        .capture_names()
+        .enumerate()
+        .skip(1)
+        .find(|(_, name)| *name != Some("label"))
+        .map(|(group, _)| group)
+}
+
+/// Splits data arriving chunk by chunk into lines (without their trailing newlines).
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn feed(&mut self, chunk: &[u8], mut line: impl FnMut(&[u8])) {
+        let mut start = 0;
+
+        self.pending.extend_from_slice(chunk);
+
+        while let Some(len) = memchr(b'\n', &self.pending[start..]) {
+            line(&self.pending[start..start + len]);
+            start += len + 1;
+        }
+
+        while self.pending.len() - start >= MAX_LINE {
+            line(&self.pending[start..start + MAX_LINE]);
+            start += MAX_LINE;
+        }
+
+        self.pending.drain(..start);
+    }
+
+    fn finish(&mut self, mut line: impl FnMut(&[u8])) {
+        if !self.pending.is_empty() {
+            line(&self.pending);
+            self.pending.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(matcher: Matcher, chunks: &[&[u8]]) -> usize {
+        let mut scanner = Scanner::new(matcher);
+
+        for chunk in chunks {
+            scanner.feed(chunk);
+        }
+
+        scanner.finish();
+        scanner.times()
+    }
+
+    #[test]
+    fn test_literal_single_chunk() {
+        assert_eq!(scan(Matcher::Literal(b"ab".to_vec()), &[b"abxabxab"]), 3);
+    }
+
+    #[test]
+    fn test_literal_across_chunks() {
+        assert_eq!(
+            scan(
+                Matcher::Literal(b"abc".to_vec()),
+                &[b"xxa", b"b", b"cxxab", b"c"]
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_literal_non_overlapping_across_chunks() {
+        // Like find_iter() on the whole data: "aaaa" contains "aa" twice
+        assert_eq!(
+            scan(Matcher::Literal(b"aa".to_vec()), &[b"a", b"aa", b"a"]),
+            2
+        );
+    }
+
+    #[test]
+    fn test_regex_across_chunks() {
+        assert_eq!(
+            scan(
+                Matcher::RegExp(Regex::new("hel+o").unwrap()),
+                &[b"he", b"llo\nhel", b"lo hello"]
+            ),
+            3
+        );
+    }
+
+    #[test]
+    fn test_regex_whole_data() {
+        // Like find_iter() on the whole data: ^ and $ refer to its start and end
+        let data: &[&[u8]] = &[b"x\ny", b"\nx\n"];
+        let scan_regex = |regex| scan(Matcher::RegExp(Regex::new(regex).unwrap()), data);
+
+        assert_eq!(scan_regex("^x$"), 0);
+        assert_eq!(scan_regex("(?m)^x$"), 2);
+        assert_eq!(scan_regex("y\nx"), 1);
+        assert_eq!(scan_regex("(?s)x.*x"), 1);
+        assert_eq!(scan_regex("\\Ax"), 1);
+        assert_eq!(scan_regex("x\n\\z"), 1);
+        assert_eq!(scan_regex("z*"), 7);
+    }
+
+    #[test]
+    fn test_regex_across_windows() {
+        let mut data = b"x".repeat(MAX_MATCH * 3);
+        data.extend_from_slice(b"\nab\n");
+        data.extend(b"y".repeat(MAX_MATCH * 3 - 1));
+        data.extend_from_slice(b"ab\nx\n");
+
+        for regex in [
+            "(?m)^x", "(?m)y$", "\\bab\\b", "ab\n", "\\Ax", "^x{5}", "x\n\\z", "y?",
+        ] {
+            let regexp = Regex::new(regex).unwrap();
+            let expected = regexp.find_iter(&data).count();
+            let chunks: Vec<_> = data.chunks(8191).collect();
+
+            assert_eq!(
+                scan(Matcher::RegExp(regexp), &chunks),
+                expected,
+                "{}",
+                regex
+            );
+        }
+    }
+
+    #[test]
+    fn test_found_unterminated_line() {
+        let mut scanner = Scanner::new(Matcher::RegExp(Regex::new("word: $").unwrap()));
+
+        scanner.feed(b"Pass");
+        assert!(!scanner.found());
+
+        scanner.feed(b"word: ");
+        assert!(scanner.found());
+        assert_eq!(scanner.times(), 0);
+    }
+
+    fn scan_lines(matcher: Matcher, lines: Lines, chunks: &[&[u8]]) -> usize {
+        let mut scanner = Scanner::lines(matcher, lines);
+
+        for chunk in chunks {
+            scanner.feed(chunk);
+        }
+
+        scanner.finish();
+        scanner.times()
+    }
+
+    #[test]
+    fn test_matching_lines() {
+        // Like grep -c: a line matching thrice counts once
+        assert_eq!(
+            scan_lines(
+                Matcher::Literal(b"x".to_vec()),
+                Lines::Matching,
+                &[b"xxx\ny\n", b"x"]
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_not_matching_lines() {
+        // Like grep -vc
+        assert_eq!(
+            scan_lines(
+                Matcher::RegExp(Regex::new("^#").unwrap()),
+                Lines::NotMatching,
+                &[b"# comment\nsetting\n", b"\n# other\n"]
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_lines_empty_input() {
+        assert_eq!(
+            scan_lines(Matcher::Literal(b"a".to_vec()), Lines::NotMatching, &[]),
+            0
+        );
+    }
+
+    fn extractor(aggregate: Aggregate, regex: &str, chunks: &[&[u8]]) -> Extractor {
+        let mut extractor = Extractor::new(Extraction {
+            regex: Regex::new(regex).unwrap(),
+            uom: "".into(),
+            aggregate,
+        });
+
+        for chunk in chunks {
+            extractor.feed(chunk);
+        }
+
+        extractor.finish();
+        extractor
+    }
+
+    fn extract_last(regex: &str, chunks: &[&[u8]]) -> Option<f64> {
+        extractor(Aggregate::Last, regex, chunks).value()
+    }
+
+    #[test]
+    fn test_extract_first_group() {
+        assert_eq!(
+            extract_last(r"used: (\d+)%", &[b"used: 4", b"2%\n"]),
+            Some(42.0)
+        );
+    }
+
+    #[test]
+    fn test_extract_named_group() {
+        assert_eq!(
+            extract_last(r"(\w+)=(?P<value>[\d.]+)", &[b"load=0.5\nload=1.25"]),
+            Some(1.25)
+        );
+    }
+
+    #[test]
+    fn test_extract_whole_match() {
+        assert_eq!(extract_last(r"-?\d+", &[b"temp -5"]), Some(-5.0));
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let data: &[&[u8]] = &[b"a 3\nb 1\nc ", b"8\nd -"];
+        let value = |aggregate| extractor(aggregate, r"\w (\d+)", data).value();
+
+        assert_eq!(value(Aggregate::Sum), Some(12.0));
+        assert_eq!(value(Aggregate::Min), Some(1.0));
+        assert_eq!(value(Aggregate::Max), Some(8.0));
+        assert_eq!(value(Aggregate::Avg), Some(4.0));
+        assert_eq!(value(Aggregate::Last), Some(8.0));
+        assert_eq!(extractor(Aggregate::Sum, r"(\d+)", &[b"x"]).value(), None);
+    }
+
+    #[test]
+    fn test_each_labelled() {
+        let ex = extractor(
+            Aggregate::Each,
+            r"(?P<label>sd\w) (\d+)",
+            &[b"sda 10\nsdb 20\nsda 30\n"],
+        );
+
+        assert_eq!(ex.count(), 3);
+        assert_eq!(
+            ex.each(),
+            &[("sda".to_owned(), 30.0), ("sdb".to_owned(), 20.0)]
+        );
+    }
+
+    #[test]
+    fn test_each_unlabelled() {
+        let ex = extractor(Aggregate::Each, r"\d+", &[b"7 9"]);
+
+        assert_eq!(ex.each(), &[("1".to_owned(), 7.0), ("2".to_owned(), 9.0)]);
+    }
+
+    #[test]
+    fn test_extract_nothing() {
+        assert_eq!(extract_last(r"used: (\S+)", &[b"used: lots\n"]), None);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(scan(Matcher::Literal(b"a".to_vec()), &[]), 0);
+        assert_eq!(scan(Matcher::RegExp(Regex::new("a").unwrap()), &[]), 0);
+    }
+}
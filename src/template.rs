@@ -0,0 +1,146 @@
+/// A text with `{NAME}` or `{NAME:FIELD}` placeholders.
+/// `{{` and `}}` stand for literal braces.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Piece {
+    Text(String),
+    /// A name and maybe the field of it after the last colon.
+    Placeholder(String, Option<String>),
+}
+
+impl Template {
+    pub(crate) fn parse(source: &str) -> Option<Self> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let rest = chars.as_str();
+
+                    if let Some(after) = rest.strip_prefix('{') {
+                        text.push('{');
+                        chars = after.chars();
+                        continue;
+                    }
+
+                    let (name, after) = rest.split_once('}')?;
+
+                    if name.contains('{') {
+                        return None;
+                    }
+
+                    let (name, field) = match name.rsplit_once(':') {
+                        None => (name, None),
+                        Some((name, field)) if !field.is_empty() => (name, Some(field.to_owned())),
+                        Some(_) => return None,
+                    };
+
+                    if name.is_empty() {
+                        return None;
+                    }
+
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+
+                    pieces.push(Piece::Placeholder(name.to_owned(), field));
+                    chars = after.chars();
+                }
+                '}' => {
+                    // A lone closing brace is ambiguous, so require it doubled
+                    chars = chars.as_str().strip_prefix('}')?.chars();
+                    text.push('}');
+                }
+                _ => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        Some(Self { pieces })
+    }
+
+    /// Fills in the placeholders. Unknown ones are kept as they are.
+    pub(crate) fn render(&self, lookup: impl Fn(&str, Option<&str>) -> Option<String>) -> String {
+        let mut rendered = String::new();
+
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => rendered.push_str(text),
+                Piece::Placeholder(name, field) => match lookup(name, field.as_deref()) {
+                    None => {
+                        rendered.push('{');
+                        rendered.push_str(name);
+
+                        if let Some(field) = field {
+                            rendered.push(':');
+                            rendered.push_str(field);
+                        }
+
+                        rendered.push('}');
+                    }
+                    Some(value) => rendered.push_str(&value),
+                },
+            }
+        }
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> Option<String> {
+        Template::parse(template).map(|template| {
+            template.render(|name, field| match (name, field) {
+                ("state", None) => Some("CRITICAL".to_owned()),
+                ("errors", None) => Some("3".to_owned()),
+                ("errors", Some("count")) => Some("2".to_owned()),
+                ("a:b", Some("count")) => Some("1".to_owned()),
+                _ => None,
+            })
+        })
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render("{state}: {errors} errors").as_deref(),
+            Some("CRITICAL: 3 errors")
+        );
+        assert_eq!(render("{{state}}").as_deref(), Some("{state}"));
+        assert_eq!(render("{other} ok").as_deref(), Some("{other} ok"));
+        assert_eq!(render("").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_render_fields() {
+        assert_eq!(
+            render("{errors:count} of {errors}").as_deref(),
+            Some("2 of 3")
+        );
+        assert_eq!(render("{a:b:count}").as_deref(), Some("1"));
+        assert_eq!(render("{state:count}").as_deref(), Some("{state:count}"));
+        assert_eq!(render("{a:b}").as_deref(), Some("{a:b}"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(render("{state"), None);
+        assert_eq!(render("{}"), None);
+        assert_eq!(render("state}"), None);
+        assert_eq!(render("{a{b}"), None);
+        assert_eq!(render("{:count}"), None);
+        assert_eq!(render("{errors:}"), None);
+    }
+}
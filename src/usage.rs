@@ -1,70 +1,478 @@
-use crate::cli::{ExpectedArg, ParseArgsError};
+use crate::cli::{ExpectedArg, ParseArgsError, Position};
+use crate::grammar::{choices, has_perfdata, Param, Slot, PARAMS};
+use crate::plugin::State;
+use crate::redact::{Redactor, MASK};
+use std::ffi::OsString;
+use std::fmt::Write;
 
-pub(crate) fn blame_user(err: ParseArgsError, consumed: usize) {
-    eprint!("☯️ ");
+/// How many arguments to show on either side of a wrong one.
+const CONTEXT: usize = 3;
 
+/// Reports all errors, each with the arguments around it, minus secrets.
+pub(crate) fn blame_user(
+    errors: Vec<(ParseArgsError, Position)>,
+    cli: &[OsString],
+    redactor: &Redactor,
+) {
+    for (err, position) in errors {
+        let past_end = matches!(err, ParseArgsError::UnexpectedEnd(_));
+
+        eprintln!(
+            "{} {}",
+            State::Unknown.marker(),
+            redactor.text(&message(err, &position))
+        );
+        eprint!("{}", redactor.text(&context(&position, past_end, cli)));
+    }
+}
+
+fn message(err: ParseArgsError, position: &Position) -> String {
     match err {
         ParseArgsError::UnexpectedEnd(ea) => {
-            eprintln!(
+            format!(
                 "Unexpected end of CLI arguments, expected {}.",
                 expected(ea)
-            );
+            )
         }
         ParseArgsError::EmptyString(ea) => {
-            eprintln!(
-                "Illegal empty string (CLI argument #{}), expected {}.",
-                consumed,
+            format!(
+                "Illegal empty string ({}), expected {}.",
+                position,
                 expected(ea)
-            );
+            )
         }
         ParseArgsError::BadUnicode(ea, er) => {
-            eprintln!(
-                "Invalid UTF-8 (CLI argument #{}), expected {}. Error: {}",
-                consumed,
+            format!(
+                "Invalid UTF-8 ({}), expected {}. Error: {}",
+                position,
                 expected(ea),
                 er
-            );
+            )
         }
         ParseArgsError::UnknownParam => {
-            eprintln!(
-                "Unknown parameter (CLI argument #{}), expected {}.",
-                consumed,
+            format!(
+                "Unknown parameter ({}), expected {}.",
+                position,
                 expected(ExpectedArg::Command)
-            );
+            )
         }
         ParseArgsError::BadThreshold(ea, er) => {
-            eprintln!(
-                "Invalid @start:end {} (CLI argument #{}): {}",
-                expected(ea),
-                consumed,
-                er
-            );
+            format!("Invalid @start:end {} ({}): {}", expected(ea), position, er)
         }
         ParseArgsError::UnknownMatcher => {
-            eprintln!(
-                "Unknown kind of pattern (CLI argument #{}), expected {}.",
-                consumed,
+            format!(
+                "Unknown kind of pattern ({}), expected {}.",
+                position,
                 expected(ExpectedArg::Matcher)
-            );
+            )
+        }
+        ParseArgsError::UnknownPatternKind => {
+            format!(
+                "Unknown kind of pattern ({}), expected {}.",
+                position,
+                expected(ExpectedArg::PatternKind)
+            )
         }
         ParseArgsError::BadRegex(er) => {
-            eprintln!(
-                "Invalid regular expression (CLI argument #{}): {}",
-                consumed, er
-            );
+            format!("Invalid regular expression ({}): {}", position, er)
+        }
+        ParseArgsError::BadSeconds => {
+            format!(
+                "Invalid number of seconds ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Seconds)
+            )
+        }
+        ParseArgsError::UnknownInput => {
+            format!(
+                "Unknown kind of input ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Input)
+            )
+        }
+        ParseArgsError::BadBytes => {
+            format!(
+                "Invalid number of bytes ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Bytes)
+            )
+        }
+        ParseArgsError::UnknownStream => {
+            format!(
+                "Unknown output stream ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Stream)
+            )
+        }
+        ParseArgsError::BadUnit => {
+            format!(
+                "Invalid unit of measurement ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Unit)
+            )
+        }
+        ParseArgsError::UnknownAggregate => {
+            format!(
+                "Unknown aggregate function ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Aggregate)
+            )
+        }
+        ParseArgsError::BadJsonPath => {
+            format!(
+                "Invalid JSON path ({}), expected {}.",
+                position,
+                expected(ExpectedArg::JsonPath)
+            )
+        }
+        ParseArgsError::UnknownFormat => {
+            format!(
+                "Unknown output format ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Format)
+            )
+        }
+        ParseArgsError::BadTemplate => {
+            format!(
+                "Invalid template ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Template)
+            )
+        }
+        ParseArgsError::UnknownShow => {
+            format!(
+                "Unknown output policy ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Show)
+            )
+        }
+        ParseArgsError::UnknownLimit => {
+            format!(
+                "Unknown kind of limit ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Limit)
+            )
+        }
+        ParseArgsError::BadCount => {
+            format!(
+                "Invalid number ({}), expected {}.",
+                position,
+                expected(ExpectedArg::Count)
+            )
+        }
+        ParseArgsError::UnknownState => {
+            format!(
+                "Unknown state ({}), expected {}.",
+                position,
+                expected(ExpectedArg::State)
+            )
+        }
+        ParseArgsError::UnreadableConfig(er) => {
+            format!("Can't read configuration file ({}): {}", position, er)
+        }
+        ParseArgsError::BadConfig(er) => {
+            format!("Invalid configuration ({}): {}", position, er)
+        }
+        ParseArgsError::AfterConfigCommand => {
+            format!(
+                "Unexpected CLI argument ({}) after a configuration file with a command, give it before \"config\".",
+                position
+            )
+        }
+    }
+}
+
+/// Shows where an error is, like a compiler: the surrounding CLI arguments
+/// with the wrong one underlined or the offending configuration file line.
+fn context(position: &Position, past_end: bool, cli: &[OsString]) -> String {
+    let mut context = String::new();
+
+    match position {
+        Position::Argument(_) if cli.is_empty() => {}
+        Position::Argument(n) => {
+            let culprit = match past_end {
+                true => cli.len(),
+                false => n.saturating_sub(1).min(cli.len() - 1),
+            };
+            let start = culprit.saturating_sub(CONTEXT);
+            let end = (culprit + CONTEXT + 1).min(cli.len());
+            let secret = secrets(cli);
+            let mut line = String::from(" ");
+            let mut underline = String::from(" ");
+
+            if start > 0 {
+                line.push_str(" ...");
+            }
+
+            for (i, arg) in cli.iter().enumerate().take(end).skip(start) {
+                let arg = match secret[i] {
+                    true => String::from_utf8_lossy(MASK).into_owned(),
+                    false => shell_quoted(arg),
+                };
+
+                line.push(' ');
+
+                if i == culprit {
+                    underline.push_str(&" ".repeat(line.chars().count() - underline.len()));
+                    underline.push_str(&"^".repeat(arg.chars().count()));
+                }
+
+                line.push_str(&arg);
+            }
+
+            if culprit == cli.len() {
+                underline.push_str(&" ".repeat(line.chars().count() + 1 - underline.len()));
+                underline.push('^');
+            } else if end < cli.len() {
+                line.push_str(" ...");
+            }
+
+            let _ = writeln!(context, "{}\n{}", line, underline);
+        }
+        Position::Line(file, n) => match std::fs::read_to_string(file.as_ref()) {
+            Err(_) => {}
+            Ok(source) => match source.lines().nth(n.saturating_sub(1)) {
+                Some(line) if *n > 0 => {
+                    let _ = writeln!(context, "  {} | {}", n, line);
+                }
+                _ => {}
+            },
+        },
+    }
+
+    context
+}
+
+/// Tells which CLI arguments are secrets by nature, i.e. redact patterns,
+/// stdin literal data and environment variable values,
+/// even if the parser didn't get to them.
+fn secrets(cli: &[OsString]) -> Vec<bool> {
+    let mut secrets = vec![false; cli.len()];
+
+    for (i, arg) in cli.iter().enumerate() {
+        let secret = match arg.to_str() {
+            Some("redact" | "env") => i + 2,
+            Some("stdin") if cli.get(i + 1).is_some_and(|kind| kind == "literal") => i + 2,
+            _ => continue,
+        };
+
+        match secrets.get_mut(secret) {
+            None => {}
+            Some(secret) => *secret = true,
+        }
+    }
+
+    secrets
+}
+
+/// Quotes an argument for a POSIX shell if necessary.
+fn shell_quoted(arg: &OsString) -> String {
+    let arg = arg.to_string_lossy();
+
+    match !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "@%+=:,./_-~^".contains(c))
+    {
+        true => arg.into_owned(),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+/// Describes all parameters or just one.
+pub(crate) fn help(param: Option<&str>) -> String {
+    let mut help = String::new();
+
+    match param {
+        None => {
+            help.push_str("Usage: check_rungrep [PARAM ARGS...] command EXE [ARGS...]\n\n");
+
+            for param in PARAMS {
+                for form in param.forms {
+                    let _ = writeln!(help, "  {}", synopsis(param, form));
+                }
+            }
+
+            help.push_str("\nRun \"check_rungrep help PARAM\" for details.\n");
+        }
+        Some(name) => {
+            for param in PARAMS.iter().filter(|param| param.name == name) {
+                let mut placeholders = Vec::new();
+
+                for form in param.forms {
+                    let _ = writeln!(help, "{}", synopsis(param, form));
+                }
+
+                let _ = writeln!(help, "\n{}", param.about);
+
+                for slot in param.forms.iter().flat_map(|form| form.iter()) {
+                    match slot {
+                        Slot::Arg(placeholder, ea) | Slot::Optional(placeholder, ea)
+                            if !placeholders.contains(placeholder) =>
+                        {
+                            if placeholders.is_empty() {
+                                help.push('\n');
+                            }
+
+                            placeholders.push(*placeholder);
+                            let _ = writeln!(help, "  {}: {}", placeholder, expected(ea.clone()));
+                        }
+                        _ => {}
+                    }
+                }
+
+                if param.forms.iter().any(|form| has_perfdata(form)) {
+                    let (uom, named_uom) = match placeholders.contains(&"UOM") {
+                        true => (" (UOM)", " (uom=UOM)"),
+                        false => ("", ""),
+                    };
+
+                    let _ = writeln!(
+                        help,
+                        "\nWARN CRIT LABEL{} may also be given as warn=WARN crit=CRIT label=LABEL{} in any order.",
+                        uom, named_uom
+                    );
+                }
+            }
         }
     }
+
+    help
 }
 
-fn expected(ea: ExpectedArg) -> &'static str {
+fn synopsis(param: &Param, form: &[Slot]) -> String {
+    let mut synopsis = param.name.to_owned();
+
+    for slot in form {
+        synopsis.push(' ');
+
+        match slot {
+            Slot::Words(words) => synopsis.push_str(&words.join("|")),
+            Slot::Choice(ea) => synopsis.push_str(&choices(ea).unwrap_or_default().join("|")),
+            Slot::Arg(placeholder, _) => synopsis.push_str(placeholder),
+            Slot::Optional(placeholder, _) => {
+                let _ = write!(synopsis, "[{}]", placeholder);
+            }
+            Slot::Rest => synopsis.push_str("[ARGS...]"),
+        }
+    }
+
+    synopsis
+}
+
+fn expected(ea: ExpectedArg) -> String {
     match ea {
-        ExpectedArg::Matcher => "\"literal\"/\"regex\"",
-        ExpectedArg::Pattern => "search pattern",
-        ExpectedArg::Warning => "warning threshold",
-        ExpectedArg::Critical => "critical threshold",
-        ExpectedArg::Label => "perfdata label",
-        ExpectedArg::Command => "\"command\"/\"cd\"/\"time\"/\"exit\"/\"stdout\"/\"stderr\"",
-        ExpectedArg::WorkDir => "working directory",
-        ExpectedArg::Exe => "executable name",
+        ExpectedArg::Matcher
+        | ExpectedArg::PatternKind
+        | ExpectedArg::State
+        | ExpectedArg::Input
+        | ExpectedArg::Stream
+        | ExpectedArg::Aggregate
+        | ExpectedArg::Format
+        | ExpectedArg::Show
+        | ExpectedArg::Limit => quoted(choices(&ea).unwrap_or_default()),
+        ExpectedArg::Command => quoted(&PARAMS.iter().map(|param| param.name).collect::<Vec<_>>()),
+        ExpectedArg::Pattern => "search pattern".to_owned(),
+        ExpectedArg::Warning => "warning threshold".to_owned(),
+        ExpectedArg::Critical => "critical threshold".to_owned(),
+        ExpectedArg::Label => "perfdata label".to_owned(),
+        ExpectedArg::WorkDir => "working directory".to_owned(),
+        ExpectedArg::Exe => "executable name".to_owned(),
+        ExpectedArg::Seconds => "positive number of seconds".to_owned(),
+        ExpectedArg::EnvName => "environment variable name".to_owned(),
+        ExpectedArg::EnvValue => "environment variable value".to_owned(),
+        ExpectedArg::InputFile => "input file".to_owned(),
+        ExpectedArg::InputData => "input data".to_owned(),
+        ExpectedArg::Bytes => "number of bytes".to_owned(),
+        ExpectedArg::JsonPath => {
+            "JSON path (e.g. \"$.items[0].size\" or \"$.items[*]\")".to_owned()
+        }
+        ExpectedArg::Prefix => "perfdata label prefix".to_owned(),
+        ExpectedArg::Template => {
+            "summary template (e.g. \"{state}: {errors:count} errors\")".to_owned()
+        }
+        ExpectedArg::Count => "number of lines/bytes".to_owned(),
+        ExpectedArg::ConfigFile => "TOML configuration file".to_owned(),
+        ExpectedArg::Unit => "unit of measurement (e.g. \"%\", \"B\", \"s\" or empty)".to_owned(),
+    }
+}
+
+/// Formats choices like `"a"/"b"`.
+fn quoted(words: &[&str]) -> String {
+    words
+        .iter()
+        .map(|word| format!("\"{}\"", word))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected() {
+        assert_eq!(expected(ExpectedArg::Stream), "\"stdout\"/\"stderr\"");
+        assert_eq!(expected(ExpectedArg::PatternKind), "\"literal\"/\"regex\"");
+        assert!(expected(ExpectedArg::Command).starts_with("\"command\"/\"config\"/\"cd\"/"));
+        assert!(expected(ExpectedArg::Command).ends_with("/\"help\"/\"version\""));
+    }
+
+    #[test]
+    fn test_context() {
+        let cli: Vec<_> = [
+            "exit", "", "x", "", "stdout", "regex", "it's", "1", "", "", "command", "true",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+
+        assert_eq!(
+            context(&Position::Argument(4), false, &cli),
+            "  exit '' x '' stdout regex 'it'\\''s' ...\n            ^^\n"
+        );
+        assert_eq!(
+            context(&Position::Argument(11), false, &cli),
+            "  ... 1 '' '' command true\n              ^^^^^^^\n"
+        );
+        assert_eq!(
+            context(&Position::Argument(12), true, &cli),
+            "  ... '' command true\n                      ^\n"
+        );
+        assert_eq!(context(&Position::Argument(0), true, &[]), "");
+
+        let cli: Vec<_> = [
+            "env", "TOKEN", "xyz", "stdin", "literal", "PW", "redact", "regex", "pw", "x",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+
+        assert_eq!(
+            context(&Position::Argument(5), false, &cli),
+            "  ... TOKEN ******** stdin literal ******** redact regex ...\n                           ^^^^^^^\n"
+        );
+        assert_eq!(
+            context(&Position::Argument(10), false, &cli),
+            "  ... redact regex ******** x\n                            ^\n"
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        let all = help(None);
+        assert!(all.contains("\n  command EXE [ARGS...]\n"));
+        assert!(all.contains("\n  stdout lines|inverted literal|regex PATTERN WARN CRIT LABEL\n"));
+        assert!(all.contains("\n  stdin null\n"));
+
+        let timeout = help(Some("timeout"));
+        assert_eq!(
+            timeout,
+            "timeout SECONDS STATE\n\nTerminate the command after SECONDS and report STATE.\n\n  SECONDS: positive number of seconds\n  STATE: \"ok\"/\"warning\"/\"critical\"/\"unknown\"\n"
+        );
+
+        assert!(help(Some("stderr")).ends_with(
+            "WARN CRIT LABEL (UOM) may also be given as warn=WARN crit=CRIT label=LABEL (uom=UOM) in any order.\n"
+        ));
     }
 }
@@ -39,6 +39,68 @@ fn test_no_args_exits_3() {
     );
 }
 
+/// All invalid parameters must be reported, each with the arguments around it.
+#[test]
+fn test_all_errors_reported() {
+    let output = check_rungrep()
+        .args([
+            "exit", "", "x", "", "stdout", "fuzzy", "ERROR", "", "1:", "", "command", "true",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("(CLI argument #3)")
+            && stderr.contains("\n  exit '' x '' stdout fuzzy ...\n          ^\n")
+            && stderr.contains("Unknown kind of pattern (CLI argument #6)")
+            && stderr
+                .contains("\n  ... x '' stdout fuzzy ERROR '' 1: ...\n                  ^^^^^\n"),
+        "stderr was: {stderr}"
+    );
+}
+
+/// Secrets don't show up among the arguments around an error.
+#[test]
+fn test_errors_hide_secrets() {
+    let output = check_rungrep()
+        .args([
+            "redact",
+            "literal",
+            "hunter2",
+            "env",
+            "TOKEN",
+            "xyz",
+            "exit",
+            "x",
+            "",
+            "",
+            "stdin",
+            "literal",
+            "PASSWORD",
+            "maxoutput",
+            "y",
+            "command",
+            "login",
+            "hunter2",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("\n  ... TOKEN ******** exit x '' '' stdin ...\n"),
+        "stderr was: {stderr}"
+    );
+    assert!(
+        stderr.contains("\n  ... literal ******** maxoutput y command login ********\n"),
+        "stderr was: {stderr}"
+    );
+    for secret in ["hunter2", "xyz", "PASSWORD"] {
+        assert!(!stderr.contains(secret), "stderr was: {stderr}");
+    }
+}
+
 /// A basic command must succeed with exit code 0.
 #[test]
 fn test_command_success() {
@@ -248,3 +310,1047 @@ fn test_stdin_env_var() {
         .unwrap();
     assert_eq!(output.status.code(), Some(0));
 }
+
+/// `timeout`: a hanging command is terminated and the configured state is reported
+/// along with the output collected so far.
+#[cfg(unix)]
+#[test]
+fn test_timeout_terminates_command() {
+    let output = check_rungrep()
+        .args([
+            "timeout",
+            "0.5",
+            "critical",
+            "command",
+            "sh",
+            "-c",
+            "echo partial; exec sleep 10",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("timed out"), "stdout was: {stdout}");
+    assert!(stdout.contains("partial"), "stdout was: {stdout}");
+}
+
+/// `timeout`: background processes holding the output open after the command exited
+/// are terminated as well and reported as such.
+#[cfg(unix)]
+#[test]
+fn test_timeout_terminates_output_holders() {
+    let output = check_rungrep()
+        .args([
+            "timeout",
+            "0.5",
+            "critical",
+            "command",
+            "sh",
+            "-c",
+            "sleep 30 & echo $!",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("left behind kept its output open"),
+        "stdout was: {stdout}"
+    );
+
+    let pid = stdout
+        .lines()
+        .find_map(|line| line.trim().parse::<u32>().ok())
+        .unwrap();
+    let ps = std::process::Command::new("ps")
+        .args(["-o", "stat=", "-p", &pid.to_string()])
+        .output()
+        .unwrap();
+    let stat = String::from_utf8_lossy(&ps.stdout);
+    assert!(
+        stat.trim().is_empty() || stat.starts_with('Z'),
+        "sleep {pid} is still running: {stat}"
+    );
+}
+
+/// `timeout`: a command finishing in time isn't affected.
+#[test]
+fn test_timeout_not_reached() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "timeout", "10", "critical", "exit", "", "0:0", "", "command", shell, flag, "exit 0",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("timed out"), "stdout was: {stdout}");
+}
+
+/// `leftovers`: background processes forked by the command are counted and terminated,
+/// so that they don't keep the output pipes open.
+#[cfg(unix)]
+#[test]
+fn test_leftovers_terminated() {
+    let output = check_rungrep()
+        .args([
+            "leftovers",
+            "",
+            "0:0",
+            "stray",
+            "command",
+            "sh",
+            "-c",
+            "sleep 30 & sleep 30 & echo started",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("left 2 processes"), "stdout was: {stdout}");
+    assert!(stdout.contains("'stray'=2"), "stdout was: {stdout}");
+}
+
+/// The command only gets its own process group if something needs it,
+/// so that killing the plugin's process group catches the command otherwise.
+#[cfg(unix)]
+#[test]
+fn test_process_group() {
+    let pgids = |args: &[&str]| {
+        let output = check_rungrep()
+            .args(args)
+            .args([
+                "show",
+                "stdout",
+                "always",
+                "command",
+                "sh",
+                "-c",
+                "ps -o pgid= -p $$; ps -o pgid= -p $PPID",
+            ])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        let pgids: Vec<_> = stdout
+            .lines()
+            .filter_map(|line| line.trim().parse::<u32>().ok())
+            .collect();
+        assert_eq!(pgids.len(), 2, "stdout was: {stdout}");
+        pgids
+    };
+
+    let pgids_alone = pgids(&[]);
+    assert_eq!(pgids_alone[0], pgids_alone[1]);
+
+    let pgids_timeout = pgids(&["timeout", "10", "critical"]);
+    assert_ne!(pgids_timeout[0], pgids_timeout[1]);
+}
+
+/// Resource usage conditions produce perfdata with proper units.
+#[cfg(unix)]
+#[test]
+fn test_resource_usage_perfdata() {
+    let output = check_rungrep()
+        .args([
+            "cputime", "", "", "cpu", "maxrss", "", "", "rss", "majflt", "", "", "faults", "ctxsw",
+            "", "", "switches", "command", "sh", "-c", "exit 0",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("seconds of CPU time"),
+        "stdout was: {stdout}"
+    );
+    assert!(stdout.contains("'cpu'="), "stdout was: {stdout}");
+    assert!(stdout.contains("B;;;0;"), "stdout was: {stdout}");
+    assert!(stdout.contains("'faults'="), "stdout was: {stdout}");
+    assert!(stdout.contains("'switches'="), "stdout was: {stdout}");
+}
+
+/// `signal`: death by signal is thresholded instead of causing UNKNOWN.
+#[cfg(unix)]
+#[test]
+fn test_signal_condition_critical() {
+    let output = check_rungrep()
+        .args([
+            "signal",
+            "",
+            "0:0",
+            "sig",
+            "command",
+            "sh",
+            "-c",
+            "echo before; kill -TERM $$",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("killed by signal 15"),
+        "stdout was: {stdout}"
+    );
+    assert!(stdout.contains("'sig'=15"), "stdout was: {stdout}");
+    assert!(stdout.contains("before"), "stdout was: {stdout}");
+}
+
+/// Without a `signal` condition, death by signal still means UNKNOWN.
+#[cfg(unix)]
+#[test]
+fn test_killed_without_signal_condition() {
+    let output = check_rungrep()
+        .args(["command", "sh", "-c", "kill -TERM $$"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("child was killed"), "stdout was: {stdout}");
+}
+
+/// `env`/`unenv`/`cleanenv` control the command's environment in order.
+#[cfg(unix)]
+#[test]
+fn test_env_control() {
+    let output = check_rungrep()
+        .env("CHECK_RUNGREP_TEST_INHERITED", "inherited")
+        .env("CHECK_RUNGREP_TEST_REMOVED", "removed")
+        .args([
+            "unenv",
+            "CHECK_RUNGREP_TEST_REMOVED",
+            "env",
+            "CHECK_RUNGREP_TEST_SET",
+            "pinned",
+            "stdout",
+            "literal",
+            "inherited pinned .",
+            "",
+            "1:1",
+            "",
+            "command",
+            "sh",
+            "-c",
+            "echo $CHECK_RUNGREP_TEST_INHERITED $CHECK_RUNGREP_TEST_SET ${CHECK_RUNGREP_TEST_REMOVED}.",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stdout was: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+/// `cleanenv` drops everything inherited, but later `env` still applies.
+#[cfg(unix)]
+#[test]
+fn test_cleanenv() {
+    let output = check_rungrep()
+        .env("CHECK_RUNGREP_TEST_INHERITED", "inherited")
+        .args([
+            "cleanenv",
+            "env",
+            "LC_ALL",
+            "C",
+            "stdout",
+            "literal",
+            "[] [C]",
+            "",
+            "1:1",
+            "",
+            "command",
+            "/bin/sh",
+            "-c",
+            "echo [$CHECK_RUNGREP_TEST_INHERITED] [$LC_ALL]",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stdout was: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+/// `stdin literal`: the data is piped to the command's stdin, overriding the env var.
+#[test]
+fn test_stdin_literal() {
+    let (shell, flag) = sh();
+    #[cfg(unix)]
+    let script = "cat";
+    #[cfg(windows)]
+    let script = "more";
+    let output = check_rungrep()
+        .env("CHECK_RUNGREP_STDIN", "from env")
+        .args([
+            "stdin", "literal", "hello", "stdout", "literal", "hello", "", "1:1", "", "stdout",
+            "literal", "from env", "", "0:0", "", "command", shell, flag, script,
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+}
+
+/// `stdin file`: the file is connected to the command's stdin.
+#[cfg(unix)]
+#[test]
+fn test_stdin_file() {
+    let path = std::env::temp_dir().join(format!("check_rungrep_stdin_{}", std::process::id()));
+    std::fs::write(&path, "from file").unwrap();
+    let output = check_rungrep()
+        .args(["stdin", "file"])
+        .arg(&path)
+        .args([
+            "stdout",
+            "literal",
+            "from file",
+            "",
+            "1:1",
+            "",
+            "command",
+            "cat",
+        ])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(0));
+}
+
+/// `stdin file` with a nonexistent file must exit with code 3 and print an open(2) error.
+#[test]
+fn test_stdin_file_not_found() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "stdin",
+            "file",
+            "this_file_does_not_exist_12345",
+            "command",
+            shell,
+            flag,
+            "exit 0",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("open(2)"), "stdout was: {stdout}");
+}
+
+/// `stdin null`: the command reads EOF right away.
+#[cfg(unix)]
+#[test]
+fn test_stdin_null() {
+    let output = check_rungrep()
+        .env("CHECK_RUNGREP_STDIN", "from env")
+        .args([
+            "stdin", "null", "stdout", "literal", "from env", "", "0:0", "", "command", "cat",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+}
+
+/// `maxoutput` caps what's captured while `bytes` still reports the total size
+/// and matching still sees all output.
+#[cfg(unix)]
+#[test]
+fn test_maxoutput_truncates() {
+    let output = check_rungrep()
+        .args([
+            "maxoutput",
+            "10",
+            "stdout",
+            "bytes",
+            "",
+            "",
+            "out_bytes",
+            "stdout",
+            "literal",
+            "y",
+            "",
+            "0:0",
+            "",
+            "command",
+            "sh",
+            "-c",
+            "yes | head -c 100000",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("'out_bytes'=100000B"),
+        "stdout was: {stdout}"
+    );
+    assert!(
+        stdout.contains("matched the following pattern 50000 times"),
+        "stdout was: {stdout}"
+    );
+    assert!(
+        stdout.contains("[99990 more bytes not captured]"),
+        "stdout was: {stdout}"
+    );
+}
+
+/// `stopon`: the command is terminated as soon as the pattern shows up.
+#[cfg(unix)]
+#[test]
+fn test_stopon_terminates_command() {
+    let start = std::time::Instant::now();
+    let output = check_rungrep()
+        .args([
+            "timeout",
+            "20",
+            "critical",
+            "stopon",
+            "stdout",
+            "literal",
+            "ready",
+            "command",
+            "sh",
+            "-c",
+            "echo ready; sleep 30",
+        ])
+        .output()
+        .unwrap();
+    assert!(start.elapsed().as_secs() < 10);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("stop pattern after"),
+        "stdout was: {stdout}"
+    );
+    assert!(!stdout.contains("timed out"), "stdout was: {stdout}");
+}
+
+/// `stopon`: output not terminated by a newline, e.g. a prompt, is matched as well.
+#[cfg(unix)]
+#[test]
+fn test_stopon_unterminated_line() {
+    let start = std::time::Instant::now();
+    let output = check_rungrep()
+        .args([
+            "timeout",
+            "20",
+            "critical",
+            "stopon",
+            "stdout",
+            "regex",
+            "[Pp]assword: $",
+            "command",
+            "sh",
+            "-c",
+            "printf 'Password: '; sleep 30",
+        ])
+        .output()
+        .unwrap();
+    assert!(start.elapsed().as_secs() < 10);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("stop pattern after"),
+        "stdout was: {stdout}"
+    );
+}
+
+/// `stopon` + `timeout`: "wait up to N seconds for X" reports the timeout if X doesn't show up.
+#[cfg(unix)]
+#[test]
+fn test_stopon_not_seen_times_out() {
+    let output = check_rungrep()
+        .args([
+            "timeout",
+            "0.5",
+            "warning",
+            "stopon",
+            "stderr",
+            "regex",
+            "^ready$",
+            "command",
+            "sh",
+            "-c",
+            "echo not ready >&2; exec sleep 30",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("timed out"), "stdout was: {stdout}");
+}
+
+/// `lines`/`inverted` count (not) matching lines like grep -c/-vc.
+#[test]
+fn test_lines_and_inverted() {
+    let (shell, flag) = sh();
+    #[cfg(unix)]
+    let script = "echo foo foo; echo bar; echo foo";
+    #[cfg(windows)]
+    let script = "echo foo foo& echo bar& echo foo";
+    let output = check_rungrep()
+        .args([
+            "stdout",
+            "lines",
+            "literal",
+            "foo",
+            "",
+            "",
+            "foo_lines",
+            "stdout",
+            "inverted",
+            "regex",
+            "foo",
+            "",
+            "",
+            "other_lines",
+            "command",
+            shell,
+            flag,
+            script,
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("'foo_lines'=2;"), "stdout was: {stdout}");
+    assert!(stdout.contains("'other_lines'=1;"), "stdout was: {stdout}");
+}
+
+/// `stdout extract`: the last number captured is thresholded and reported as perfdata.
+#[test]
+fn test_extract() {
+    let (shell, flag) = sh();
+    #[cfg(unix)]
+    let script = "echo used: 93%; echo used: 42%";
+    #[cfg(windows)]
+    let script = "echo used: 93%& echo used: 42%";
+    let output = check_rungrep()
+        .args([
+            "stdout",
+            "extract",
+            r"used: (\d+)%",
+            "80",
+            "",
+            "usage",
+            "%",
+            "command",
+            shell,
+            flag,
+            script,
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("'usage'=42%;"), "stdout was: {stdout}");
+}
+
+/// `stdout extract` without any number in the output must exit with code 3.
+#[test]
+fn test_extract_nothing_is_unknown() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "stdout",
+            "extract",
+            r"used: (\d+)%",
+            "",
+            "",
+            "usage",
+            "%",
+            "command",
+            shell,
+            flag,
+            "echo hello",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("contained no number"),
+        "stdout was: {stdout}"
+    );
+}
+
+/// `stdout aggregate`: numbers are collapsed (`avg`) or reported per label (`each`).
+#[test]
+fn test_aggregate() {
+    let (shell, flag) = sh();
+    #[cfg(unix)]
+    let script = "echo sda 10; echo sdb 30";
+    #[cfg(windows)]
+    let script = "echo sda 10& echo sdb 30";
+    let output = check_rungrep()
+        .args([
+            "stdout",
+            "aggregate",
+            "avg",
+            r"\w+ (\d+)",
+            "",
+            "",
+            "avg",
+            "",
+            "stdout",
+            "aggregate",
+            "each",
+            r"(?P<label>\w+) (\d+)",
+            "",
+            "20",
+            "disk",
+            "B",
+            "command",
+            shell,
+            flag,
+            script,
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("reported 30B for sdb."),
+        "stdout was: {stdout}"
+    );
+    assert!(stdout.contains("'avg'=20;"), "stdout was: {stdout}");
+    assert!(stdout.contains("'disk_sda'=10B;"), "stdout was: {stdout}");
+    assert!(stdout.contains("'disk_sdb'=30B;"), "stdout was: {stdout}");
+}
+
+/// `stdout json`: a path counts the nodes it selects, or takes the value of a single one.
+#[test]
+fn test_json() {
+    let (shell, flag) = sh();
+    #[cfg(unix)]
+    let script = r#"echo '{"items": [{"size": 3}, {"size": 5}], "ok": true}'"#;
+    #[cfg(windows)]
+    let script = r#"echo {"items": [{"size": 3}, {"size": 5}], "ok": true}"#;
+    let output = check_rungrep()
+        .args([
+            "stdout",
+            "json",
+            "$.items[*]",
+            "",
+            "",
+            "items",
+            "stdout",
+            "json",
+            "$.items[1].size",
+            "",
+            "",
+            "size",
+            "stdout",
+            "json",
+            "$.ok",
+            "",
+            "1:1",
+            "",
+            "command",
+            shell,
+            flag,
+            script,
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("'items'=2;"), "stdout was: {stdout}");
+    assert!(stdout.contains("'size'=5;"), "stdout was: {stdout}");
+}
+
+/// `stdout json` on output that isn't JSON must exit with code 3.
+#[test]
+fn test_json_malformed_is_unknown() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "stdout",
+            "json",
+            "$.size",
+            "",
+            "",
+            "size",
+            "command",
+            shell,
+            flag,
+            "echo hello",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("isn't valid JSON"), "stdout was: {stdout}");
+}
+
+/// `passthrough`: the wrapped plugin's state, text and prefixed perfdata are taken over.
+#[test]
+fn test_passthrough() {
+    let (shell, flag) = sh();
+    #[cfg(unix)]
+    let script = "echo 'DISK WARNING - 90% used | used=90%;80;95;0;100'; exit 1";
+    #[cfg(windows)]
+    let script = "echo DISK WARNING - 90% used ^| used=90%;80;95;0;100& exit 1";
+    let output = check_rungrep()
+        .args([
+            "passthrough",
+            "disk_",
+            "exit",
+            "",
+            "",
+            "exit",
+            "command",
+            shell,
+            flag,
+            script,
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Command returned state 1. DISK WARNING - 90% used"),
+        "stdout was: {stdout}"
+    );
+    assert!(stdout.contains("'disk_used'=90%;"), "stdout was: {stdout}");
+    assert!(stdout.contains("'exit'=1;"), "stdout was: {stdout}");
+}
+
+/// `output json`: state, alerts, perfdata and streams are printed as one JSON object.
+#[test]
+fn test_output_json() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "output", "json", "stdout", "literal", "foo", "", "0:0", "foos", "command", shell,
+            flag, "echo foo",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["state"], "critical");
+    assert_eq!(json["code"], 2);
+    assert_eq!(json["command"][0], shell);
+    assert_eq!(json["alerts"][0]["kind"], "matches");
+    assert_eq!(json["alerts"][0]["state"], "critical");
+    assert_eq!(json["alerts"][0]["count"], 1);
+    assert_eq!(json["alerts"][0]["pattern"], "foo");
+    assert_eq!(json["perfdata"][0]["label"], "foos");
+    assert_eq!(json["perfdata"][0]["value"], 1.0);
+    assert!(json["stdout"]["data"].as_str().unwrap().starts_with("foo"));
+}
+
+/// `output json` also reports a command which can't be run as a JSON object.
+#[test]
+fn test_output_json_unknown() {
+    let output = check_rungrep()
+        .args(["output", "json", "command", "nonexistent_binary_xyz"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["state"], "unknown");
+    assert_eq!(json["code"], 3);
+    assert!(json["message"].as_str().unwrap().starts_with("exec(3): "));
+}
+
+/// `ascii` and `CHECK_RUNGREP_ASCII` replace the state emojis, also in parse errors.
+#[test]
+fn test_ascii() {
+    let output = check_rungrep()
+        .args(["ascii", "command", "nonexistent_binary_xyz"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("[UNKNOWN] exec(3): "),
+        "stdout was: {stdout}"
+    );
+
+    let output = check_rungrep()
+        .env("CHECK_RUNGREP_ASCII", "1")
+        .args(["nonexistent_param"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("[UNKNOWN] "), "stderr was: {stderr}");
+
+    let output = check_rungrep()
+        .args(["exit", "x", "", "", "ascii", "command", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("[UNKNOWN] "), "stderr was: {stderr}");
+    assert!(!stderr.contains('\u{262F}'), "stderr was: {stderr}");
+}
+
+/// `summary`: the rendered template becomes the first output line, built-ins shadowing labels.
+#[test]
+fn test_summary() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "summary",
+            "{state}: exit {exit}, {foos} foos{{}}, {state:count} o",
+            "stdout",
+            "literal",
+            "foo",
+            "",
+            "0:0",
+            "foos",
+            "stdout",
+            "literal",
+            "o",
+            "",
+            "",
+            "state",
+            "command",
+            shell,
+            flag,
+            "echo foo",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("🚨 CRITICAL: exit 0, 1 foos{}, 2 o"));
+    assert!(lines
+        .next()
+        .unwrap()
+        .contains("matched the following pattern 1 times"));
+}
+
+/// `verbose`: alerts of all states are shown, worst first.
+#[test]
+fn test_verbose() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "verbose", "exit", "", "0:0", "", "stdout", "literal", "foo", "", "0:0", "", "command",
+            shell, flag, "echo foo",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("🚨 Command's stdout matched"));
+    assert!(lines.next().unwrap().starts_with("✅ Command returned 0."));
+}
+
+/// `show`, `showmatching` and `showlimit` control which output is dumped and how much.
+#[cfg(unix)]
+#[test]
+fn test_show_options() {
+    let output = check_rungrep()
+        .args([
+            "show",
+            "stderr",
+            "never",
+            "show",
+            "stdout",
+            "always",
+            "showmatching",
+            "0",
+            "showlimit",
+            "lines",
+            "1",
+            "1",
+            "stdout",
+            "literal",
+            "x",
+            "",
+            "",
+            "",
+            "command",
+            "sh",
+            "-c",
+            "echo x1; echo a; echo x2; echo x3; echo b >&2",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("STDOUT\n======\n\nx1\n[... 2 lines omitted ...]\nx3\n"),
+        "stdout was: {stdout}"
+    );
+    assert!(!stdout.contains("STDERR"), "stdout was: {stdout}");
+}
+
+/// `redact literal`: the secret is masked in alerts and the output dump.
+#[test]
+fn test_redact() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "redact",
+            "literal",
+            "hunter2",
+            "stdout",
+            "literal",
+            "hunter2",
+            "",
+            "0:0",
+            "",
+            "command",
+            shell,
+            flag,
+            "echo password: hunter2",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("hunter2"), "stdout was: {stdout}");
+    assert!(
+        stdout.contains("Literal string: ********"),
+        "stdout was: {stdout}"
+    );
+    assert!(
+        stdout.contains("password: ********"),
+        "stdout was: {stdout}"
+    );
+}
+
+/// `config`: parameters come from a TOML file, errors point at its lines.
+#[test]
+fn test_config() {
+    let (shell, flag) = sh();
+    let path = std::env::temp_dir().join(format!("check_rungrep_config_{}", std::process::id()));
+    std::fs::write(
+        &path,
+        format!(
+            "command = [{:?}, {:?}, \"echo healthy\"]\n\n[[stdout]]\nkind = \"literal\"\npattern = \"healthy\"\ncrit = \"0:0\"\n",
+            shell, flag
+        ),
+    )
+    .unwrap();
+    let output = check_rungrep().arg("config").arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Literal string: healthy"),
+        "stdout was: {stdout}"
+    );
+
+    let output = check_rungrep()
+        .arg("config")
+        .arg(&path)
+        .args(["exit", "", "1:", "rc"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unexpected CLI argument (CLI argument #3) after a configuration file"),
+        "stderr was: {stderr}"
+    );
+
+    std::fs::write(&path, "\n[[stdout]]\nkind = \"regex\"\npattern = \"(\"\n").unwrap();
+    let output = check_rungrep()
+        .arg("config")
+        .arg(&path)
+        .args(["command", "true"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "Invalid regular expression ({}:4)",
+            path.display()
+        )),
+        "stderr was: {stderr}"
+    );
+}
+
+/// `warn=`, `crit=` and `label=` may replace positional perfdata arguments.
+#[test]
+fn test_named_perfdata() {
+    let (shell, flag) = sh();
+    let output = check_rungrep()
+        .args([
+            "exit", "label=rc", "warn=0:0", "command", shell, flag, "exit 1",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("'rc'=1;0:0;;;"), "stdout was: {stdout}");
+}
+
+/// `help` lists all parameters or explains one, `version` prints the version.
+#[test]
+fn test_help_and_version() {
+    let output = check_rungrep().args(["help"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  redact literal|regex PATTERN\n"),
+        "stdout was: {stdout}"
+    );
+
+    let output = check_rungrep().args(["help", "exit"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("exit WARN CRIT LABEL\n"),
+        "stdout was: {stdout}"
+    );
+
+    let output = check_rungrep().args(["version"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout,
+        format!("check_rungrep {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+/// `validate` explains the conditions without running the command and exits with 0.
+#[test]
+fn test_validate() {
+    let output = check_rungrep()
+        .args([
+            "validate",
+            "exit",
+            "",
+            "0:0",
+            "",
+            "command",
+            "/nonexistent/check_rungrep_validate",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("Would run: /nonexistent/check_rungrep_validate\n"),
+        "stdout was: {stdout}"
+    );
+    assert!(
+        stdout.contains("   Critical unless 0 (0:0). Not reported as perfdata.\n"),
+        "stdout was: {stdout}"
+    );
+
+    let output = check_rungrep()
+        .args(["validate", "exit", "", "x", "", "command", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+}